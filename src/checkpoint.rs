@@ -1,15 +1,71 @@
-use std::sync::Arc;
-
 use anyhow::Result;
 use ore_boost_api::state::Stake;
 use ore_boost_api::{consts::CHECKPOINT_INTERVAL, state::Checkpoint};
-use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signer::Signer};
+use solana_sdk::{
+    address_lookup_table::AddressLookupTableAccount, instruction::Instruction, pubkey::Pubkey,
+    signer::Signer,
+};
 
-use crate::client::{AsyncClient, Client};
-use crate::error::Error::ClockStillTicking;
+use crate::client::{self, Client, MAX_COMPUTE_UNITS};
+use crate::error::Error::{
+    IncompleteCheckpoint, InstructionTooLarge, UnconfirmedTransactions,
+    UnexpectedCheckpointProgress,
+};
 use crate::lookup_tables;
+use crate::scheduler::Scheduler;
+use crate::stakes::StakeIndex;
+use crate::submit::{self, Submitter};
+
+/// times a checkpoint is resumed after failed batches
+const MAX_RESUMES: u32 = 3;
+
+/// checkpoint polls, two seconds apart, verifying a landed batch
+const MAX_VERIFY_POLLS: u32 = 10;
 
-const MAX_ACCOUNTS_PER_TX: usize = 38;
+/// lookup tables a rebase transaction may reference
+const MAX_LOOKUP_TABLES_PER_TX: usize = 2;
+
+/// stake accounts rebased in one transaction,
+/// with the lookup tables that hold them
+#[derive(Debug, Default)]
+struct Batch {
+    stake_accounts: Vec<Pubkey>,
+    instructions: Vec<Instruction>,
+    lookup_tables: Vec<AddressLookupTableAccount>,
+}
+
+impl Batch {
+    /// add the rebase if it fits, returns false otherwise
+    fn try_push(
+        &mut self,
+        signer: &Pubkey,
+        account: &Pubkey,
+        ix: Instruction,
+        lut: Option<&AddressLookupTableAccount>,
+        max_rebases: usize,
+    ) -> Result<bool> {
+        if self.instructions.len().ge(&max_rebases) {
+            return Ok(false);
+        }
+        let new_lut = lut.filter(|lut| !self.lookup_tables.iter().any(|l| l.key.eq(&lut.key)));
+        if new_lut.is_some() && self.lookup_tables.len().ge(&MAX_LOOKUP_TABLES_PER_TX) {
+            return Ok(false);
+        }
+        self.instructions.push(ix);
+        if let Some(lut) = new_lut {
+            self.lookup_tables.push(lut.clone());
+        }
+        if client::fits_in_transaction(signer, &self.instructions, &self.lookup_tables)? {
+            self.stake_accounts.push(*account);
+            return Ok(true);
+        }
+        self.instructions.pop();
+        if new_lut.is_some() {
+            self.lookup_tables.pop();
+        }
+        Ok(false)
+    }
+}
 
 pub async fn run(client: &Client, mint: &Pubkey) -> Result<()> {
     // derive address
@@ -18,9 +74,26 @@ pub async fn run(client: &Client, mint: &Pubkey) -> Result<()> {
     // get accounts
     let _boost = client.rpc.get_boost(&boost_pda).await?;
     let mut checkpoint = client.rpc.get_checkpoint(&checkpoint_pda).await?;
-    let _time = check_for_time(client, &checkpoint, &boost_pda).await;
+    // index stake accounts
+    let stakes = StakeIndex::new(client, &boost_pda).await?;
     // sync lookup tables
-    let (mut lookup_tables, mut stake_accounts) = lookup_tables::sync(client, &boost_pda).await?;
+    let (luts, _) = lookup_tables::sync(client, &boost_pda, &stakes).await?;
+    let mut index = lookup_tables::index(client, luts.as_slice()).await?;
+    // submission strategy
+    let config = client.config.boost(mint);
+    let mut submitter = Submitter::new(&boost_pda, config.strategy, config.fallback);
+    log::info!(
+        "{:?} -- submitting via {:?}, falling back to {:?}",
+        boost_pda,
+        config.strategy,
+        config.fallback
+    );
+    // sleeps until the next checkpoint is due
+    let scheduler = Scheduler::new(
+        client.pubsub.clone(),
+        config.margin_secs,
+        config.jitter_secs,
+    );
     // start checkpoint loop
     // 1) fetch checkpoint
     // 2) check for checkpoint interval
@@ -34,16 +107,37 @@ pub async fn run(client: &Client, mint: &Pubkey) -> Result<()> {
             Ok(cp) => {
                 // if new checkpoint, sync lookup tables
                 if cp.ts.ne(&checkpoint.ts) {
+                    // collect obsolete lookup tables
+                    if client.config.luts.gc {
+                        if let Err(err) = gc(client, &boost_pda, &stakes).await {
+                            log::error!("{:?} -- {:?}", boost_pda, err);
+                        }
+                    }
+                    // repack fragmented lookup tables
+                    if client.config.luts.compact {
+                        if let Err(err) = compact(client, &boost_pda, &stakes).await {
+                            log::error!("{:?} -- {:?}", boost_pda, err);
+                        }
+                    }
                     // sync lookup tables
-                    match lookup_tables::sync(client, &boost_pda).await {
-                        Ok((luts, sa)) => {
-                            lookup_tables = luts;
-                            stake_accounts = sa;
+                    let synced = match lookup_tables::sync(client, &boost_pda, &stakes).await {
+                        Ok((luts, _)) => lookup_tables::index(client, luts.as_slice()).await,
+                        Err(err) => Err(err),
+                    };
+                    match synced {
+                        Ok(idx) => {
+                            index = idx;
                             checkpoint = cp;
+                            // retry the primary strategy each checkpoint
+                            submitter.reset();
+                            client.tipper.reset_checkpoint(&boost_pda);
                         }
                         Err(err) => {
                             log::error!("{:?} -- {:?}", boost_pda, err);
-                            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+                            tokio::time::sleep(tokio::time::Duration::from_secs(
+                                client.config.retry_secs,
+                            ))
+                            .await;
                             continue;
                         }
                     }
@@ -51,53 +145,94 @@ pub async fn run(client: &Client, mint: &Pubkey) -> Result<()> {
             }
             Err(err) => {
                 log::error!("{:?} -- {:?}", boost_pda, err);
-                tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+                tokio::time::sleep(tokio::time::Duration::from_secs(client.config.retry_secs))
+                    .await;
                 continue;
             }
         }
-        // check for time
-        if let Err(err) = check_for_time(client, &checkpoint, &boost_pda).await {
-            // time has not elapsed or error
-            log::info!("{:?} -- {:?}", boost_pda, err);
-            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-            continue;
+        // check for time,
+        // sleeping until the interval elapses if not yet
+        match time_remaining(client, &checkpoint, &boost_pda).await {
+            Ok(0) => {}
+            Ok(remaining) => {
+                scheduler.wait(&boost_pda, &checkpoint_pda, remaining).await;
+                continue;
+            }
+            Err(err) => {
+                log::error!("{:?} -- {:?}", boost_pda, err);
+                tokio::time::sleep(tokio::time::Duration::from_secs(client.config.retry_secs))
+                    .await;
+                continue;
+            }
         }
-        // filter stake accounts
-        // against the checkpoint current-id,
+        // rebase all stake accounts,
         // recovering from a partial checkpoint if necessary
-        let remaining_stake_accounts =
-            filter_stake_accounts(stake_accounts.as_slice(), &checkpoint, &boost_pda);
-        // rebase all stake accounts
-        if let Err(err) = rebase_all(
-            client,
-            mint,
-            &boost_pda,
-            remaining_stake_accounts.as_slice(),
-            lookup_tables.as_slice(),
-        )
-        .await
+        if let Err(err) =
+            rebase_all(client, mint, &boost_pda, &stakes, &index, &mut submitter).await
         {
             log::error!("{:?} -- {:?}", boost_pda, err);
-            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+            tokio::time::sleep(tokio::time::Duration::from_secs(client.config.retry_secs)).await;
+        }
+        // nothing landed, so the checkpoint stays due,
+        // simulate again next interval
+        if client.config.dry_run {
+            scheduler
+                .wait(&boost_pda, &checkpoint_pda, CHECKPOINT_INTERVAL as u64)
+                .await;
         }
     }
 }
 
+/// rebase the checkpoint once if it is due,
+/// returns false without submitting if not yet
+pub async fn rebase_once(client: &Client, mint: &Pubkey) -> Result<bool> {
+    let (boost_pda, _) = ore_boost_api::state::boost_pda(*mint);
+    let (checkpoint_pda, _) = ore_boost_api::state::checkpoint_pda(boost_pda);
+    let checkpoint = client.rpc.get_checkpoint(&checkpoint_pda).await?;
+    if time_remaining(client, &checkpoint, &boost_pda)
+        .await?
+        .gt(&0)
+    {
+        return Ok(false);
+    }
+    let stakes = StakeIndex::new(client, &boost_pda).await?;
+    let (luts, _) = lookup_tables::sync(client, &boost_pda, &stakes).await?;
+    let index = lookup_tables::index(client, luts.as_slice()).await?;
+    let config = client.config.boost(mint);
+    let mut submitter = Submitter::new(&boost_pda, config.strategy, config.fallback);
+    rebase_all(client, mint, &boost_pda, &stakes, &index, &mut submitter).await?;
+    Ok(true)
+}
+
+/// collect obsolete lookup tables against the indexed stake accounts
+async fn gc(client: &Client, boost: &Pubkey, stakes: &StakeIndex) -> Result<()> {
+    let stake_accounts = stakes.stake_accounts(client).await?;
+    lookup_tables::gc(client, boost, stake_accounts.as_slice()).await?;
+    Ok(())
+}
+
+/// repack lookup tables around the indexed stake accounts
+async fn compact(client: &Client, boost: &Pubkey, stakes: &StakeIndex) -> Result<()> {
+    let stake_accounts = stakes.stake_accounts(client).await?;
+    lookup_tables::compact(client, boost, stake_accounts.as_slice()).await?;
+    Ok(())
+}
+
 /// filter stake accounts against checkpoint current-id
 fn filter_stake_accounts(
     stake_accounts: &[(Pubkey, Stake)],
     checkpoint: &Checkpoint,
     boost_pda: &Pubkey,
 ) -> Vec<Pubkey> {
-    let remaining_accounts: Vec<_> = stake_accounts
+    // rebase must proceed in stake id order
+    let mut remaining_accounts: Vec<_> = stake_accounts
         .iter()
-        .filter_map(|(pubkey, stake)| {
-            if stake.id >= checkpoint.current_id {
-                Some(*pubkey)
-            } else {
-                None
-            }
-        })
+        .filter(|(_, stake)| stake.id >= checkpoint.current_id)
+        .collect();
+    remaining_accounts.sort_by_key(|(_, stake)| stake.id);
+    let remaining_accounts: Vec<_> = remaining_accounts
+        .into_iter()
+        .map(|(pubkey, _)| *pubkey)
         .collect();
     log::info!(
         "{:?} -- checkpoint current id: {:?}",
@@ -112,12 +247,12 @@ fn filter_stake_accounts(
     remaining_accounts
 }
 
-/// check if enough time has passed since last checkpoint
-async fn check_for_time(
+/// seconds until the checkpoint interval elapses, zero if it has
+pub async fn time_remaining(
     client: &Client,
     checkpoint: &Checkpoint,
     boost_pda: &Pubkey,
-) -> Result<()> {
+) -> Result<u64> {
     log::info!("{:?} -- checking if interval has elapsed", boost_pda);
     let clock = client.rpc.get_clock().await?;
     let time_since_last = clock.unix_timestamp - checkpoint.ts;
@@ -127,18 +262,166 @@ async fn check_for_time(
             boost_pda,
             CHECKPOINT_INTERVAL - time_since_last
         );
-        return Err(anyhow::anyhow!(ClockStillTicking));
+        return Ok((CHECKPOINT_INTERVAL - time_since_last) as u64);
     }
     log::info!("{:?} -- interval elapsed", boost_pda);
-    Ok(())
+    Ok(0)
 }
 
+/// pack rebase instructions into transactions
+///
+/// keeps id order, and greedily fills each transaction
+/// up to the packet size and compute unit limits.
+/// starts a new transaction rather than pull in more than the max lookup tables,
+/// so each transaction references only the tables its stake accounts are in.
+/// stake accounts without a lookup table are referenced directly.
+/// the batch size caps rebases per transaction below that, if set.
+fn pack(
+    signer: &Pubkey,
+    mint: &Pubkey,
+    stake_accounts: &[Pubkey],
+    index: &lookup_tables::Index,
+    units_per_rebase: u64,
+    batch_size: Option<usize>,
+) -> Result<Vec<Batch>> {
+    let max_rebases = (MAX_COMPUTE_UNITS as u64 / units_per_rebase.max(1)) as usize;
+    let max_rebases = batch_size.map_or(max_rebases, |batch_size| batch_size.min(max_rebases));
+    let mut batches: Vec<Batch> = vec![];
+    for account in stake_accounts {
+        let ix = ore_boost_api::sdk::rebase(*signer, *mint, *account);
+        let lut = index.get(account);
+        if let Some(batch) = batches.last_mut() {
+            if batch.try_push(signer, account, ix.clone(), lut, max_rebases)? {
+                continue;
+            }
+        }
+        let mut batch = Batch::default();
+        if !batch.try_push(signer, account, ix, lut, max_rebases)? {
+            return Err(anyhow::anyhow!(InstructionTooLarge));
+        }
+        batches.push(batch);
+    }
+    Ok(batches)
+}
+
+/// verify the checkpoint advanced by exactly the rebased stake accounts,
+/// or completed if they were the last.
+/// polls while the rpc catches up with the landed slot,
+/// so progress still missing after is a partial landing, fork or rollback.
+/// past the last without completing, stake accounts are missing from the index.
+async fn verify_progress(
+    client: &Client,
+    boost: &Pubkey,
+    start: &Checkpoint,
+    rebased: u64,
+    last: bool,
+) -> Result<()> {
+    let (checkpoint_pda, _) = ore_boost_api::state::checkpoint_pda(*boost);
+    let expected = start.current_id + rebased;
+    let mut actual = start.current_id;
+    for _ in 0..MAX_VERIFY_POLLS {
+        let checkpoint = client.rpc.get_checkpoint(&checkpoint_pda).await?;
+        if checkpoint.ts.ne(&start.ts) {
+            // checkpoint completed
+            if last {
+                return Ok(());
+            }
+            return Err(anyhow::anyhow!(UnexpectedCheckpointProgress {
+                expected,
+                actual: checkpoint.current_id
+            }));
+        }
+        actual = checkpoint.current_id;
+        match actual.cmp(&expected) {
+            // the last rebase completes the checkpoint in the same instruction
+            std::cmp::Ordering::Equal if last => {
+                return Err(anyhow::anyhow!(IncompleteCheckpoint(actual)));
+            }
+            std::cmp::Ordering::Equal => return Ok(()),
+            std::cmp::Ordering::Greater => break,
+            std::cmp::Ordering::Less => {}
+        }
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+    }
+    log::error!(
+        "{:?} -- checkpoint at id {}, expected {}",
+        boost,
+        actual,
+        expected
+    );
+    Err(anyhow::anyhow!(UnexpectedCheckpointProgress {
+        expected,
+        actual
+    }))
+}
+
+/// rebase every stake account left in the checkpoint
+///
+/// reads stake accounts from the index as of the rebase.
+/// after a failed batch, re-reads the checkpoint,
+/// reconciles the index in case it missed a staker,
+/// and resumes from the checkpoint's current id with the same lookup tables.
+/// in a dry run, simulates a single pass instead
 async fn rebase_all(
     client: &Client,
     mint: &Pubkey,
     boost: &Pubkey,
+    stakes: &StakeIndex,
+    index: &lookup_tables::Index,
+    submitter: &mut Submitter,
+) -> Result<()> {
+    let (checkpoint_pda, _) = ore_boost_api::state::checkpoint_pda(*boost);
+    let start = client.rpc.get_checkpoint(&checkpoint_pda).await?;
+    let mut checkpoint = start;
+    let mut resumes = 0;
+    loop {
+        // filter stake accounts against the checkpoint current-id
+        let stake_accounts = stakes.stake_accounts(client).await?;
+        let remaining = filter_stake_accounts(stake_accounts.as_slice(), &checkpoint, boost);
+        let err = match rebase_remaining(
+            client,
+            mint,
+            boost,
+            &checkpoint,
+            remaining.as_slice(),
+            index,
+            submitter,
+        )
+        .await
+        {
+            Ok(()) => return Ok(()),
+            // nothing landed to resume from
+            Err(err) if client.config.dry_run => return Err(err),
+            Err(err) => err,
+        };
+        resumes += 1;
+        if resumes.gt(&MAX_RESUMES) {
+            return Err(err);
+        }
+        log::error!("{:?} -- {:?}", boost, err);
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        checkpoint = client.rpc.get_checkpoint(&checkpoint_pda).await?;
+        if checkpoint.ts.ne(&start.ts) {
+            log::info!("{:?} -- checkpoint completed while resuming", boost);
+            return Ok(());
+        }
+        stakes.reconcile(client).await?;
+        log::info!(
+            "{:?} -- resuming checkpoint from id {}",
+            boost,
+            checkpoint.current_id
+        );
+    }
+}
+
+async fn rebase_remaining(
+    client: &Client,
+    mint: &Pubkey,
+    boost: &Pubkey,
+    start: &Checkpoint,
     stake_accounts: &[Pubkey],
-    lookup_tables: &[Pubkey],
+    index: &lookup_tables::Index,
+    submitter: &mut Submitter,
 ) -> Result<()> {
     log::info!("{:?} -- rebasing stake accounts", boost);
     // pack instructions for rebase
@@ -154,27 +437,373 @@ async fn rebase_all(
         let sig = client.send_transaction(&[ix]).await?;
         log::info!("{:?} -- reset signature: {:?}", boost, sig);
     } else {
-        // chunk stake accounts into batches
-        let mut bundles: Vec<Vec<Instruction>> = vec![];
-        for chunk in stake_accounts.chunks(MAX_ACCOUNTS_PER_TX) {
-            // build transaction
-            let mut transaction = vec![];
-            for account in chunk {
-                let signer = Arc::clone(&client.keypair);
-                transaction.push(ore_boost_api::sdk::rebase(signer.pubkey(), *mint, *account));
-            }
-            bundles.push(transaction);
+        // estimate compute units per rebase from the first
+        let signer = client.keypair.pubkey();
+        let first = ore_boost_api::sdk::rebase(signer, *mint, stake_accounts[0]);
+        let units = client.estimate_compute_units(&[first]).await?;
+        let units_per_rebase = units + units / 10;
+        // pack stake accounts into batches by size and lookup table
+        let batch_size = client.config.boost(mint).batch_size;
+        let mut bundles: Vec<(Vec<Instruction>, Vec<Pubkey>)> = vec![];
+        for batch in pack(
+            &signer,
+            mint,
+            stake_accounts,
+            index,
+            units_per_rebase,
+            batch_size,
+        )? {
+            let luts = batch.lookup_tables.iter().map(|lut| lut.key).collect();
+            bundles.push((batch.instructions, luts));
         }
-        // bundle transactions
-        for tx in bundles.chunks(4) {
-            let bundle: Vec<&[Instruction]> = tx.iter().map(|vec| vec.as_slice()).collect();
-            log::info!("{:?} -- submitting rebase", boost);
-            let bundle_id = client
-                .send_jito_bundle_with_luts(bundle.as_slice(), lookup_tables)
-                .await?;
-            log::info!("{:?} -- confirmed rebase bundle id: {:?}", boost, bundle_id);
+        log::info!(
+            "{:?} -- packed {} stake accounts into {} transactions",
+            boost,
+            stake_accounts.len(),
+            bundles.len()
+        );
+        // submit transactions in groups,
+        // verifying each against the checkpoint before the next
+        let groups: Vec<Vec<(&[Instruction], &[Pubkey])>> = bundles
+            .chunks(4)
+            .map(|tx| {
+                tx.iter()
+                    .map(|(ixs, luts)| (ixs.as_slice(), luts.as_slice()))
+                    .collect()
+            })
+            .collect();
+        let mut rebased = 0;
+        let mut prebuilt = None;
+        for (i, group) in groups.iter().enumerate() {
+            let strategy = submitter.strategy();
+            log::info!("{:?} -- submitting rebase via {:?}", boost, strategy);
+            // sign the next group while this one is in flight,
+            // so it is sent as soon as this one is verified
+            let next = groups.get(i + 1);
+            let (signatures, next) = tokio::join!(
+                submitter.submit(client, group.as_slice(), prebuilt.take()),
+                async {
+                    match next {
+                        Some(next) => {
+                            submit::prebuild(client, boost, strategy, next.as_slice()).await
+                        }
+                        None => None,
+                    }
+                }
+            );
+            let signatures = signatures?;
+            prebuilt = next;
+            rebased += group.iter().map(|(ixs, _)| ixs.len() as u64).sum::<u64>();
+            // nothing landed to verify
+            if client.config.dry_run {
+                continue;
+            }
+            if client.config.verify_signatures
+                && !client
+                    .rpc
+                    .signatures_confirmed(signatures.as_slice())
+                    .await?
+            {
+                return Err(anyhow::anyhow!(UnconfirmedTransactions));
+            }
+            let last = rebased.eq(&(stake_accounts.len() as u64));
+            verify_progress(client, boost, start, rebased, last).await?;
         }
     }
+    if client.config.dry_run {
+        log::info!("{:?} -- checkpoint simulated", boost);
+        return Ok(());
+    }
     log::info!("{:?} -- checkpoint complete", boost);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+
+    use super::*;
+    use crate::mock::MockChain;
+    use crate::submit::Strategy;
+
+    #[test]
+    fn filter_stake_accounts_resumes_in_id_order() {
+        let boost = Pubkey::new_unique();
+        let stake_accounts = [7, 2, 5, 0, 9]
+            .into_iter()
+            .map(|id| {
+                let mut stake = Stake::zeroed();
+                stake.boost = boost;
+                stake.id = id;
+                (Pubkey::new_unique(), stake)
+            })
+            .collect::<Vec<_>>();
+        let mut checkpoint = Checkpoint::zeroed();
+        checkpoint.current_id = 5;
+        let remaining = filter_stake_accounts(stake_accounts.as_slice(), &checkpoint, &boost);
+        let expected = [5, 7, 9]
+            .into_iter()
+            .map(|id| stake_accounts.iter().find(|(_, s)| s.id == id).unwrap().0)
+            .collect::<Vec<_>>();
+        assert_eq!(remaining, expected);
+    }
+
+    #[test]
+    fn pack_fills_transactions_by_lookup_table() {
+        let signer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        // 50 in the first table, 10 in the second, 10 in the third, 5 untabled
+        let stake_accounts = (0..75).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+        let luts = [0..50, 50..60, 60..70]
+            .into_iter()
+            .map(|range| AddressLookupTableAccount {
+                key: Pubkey::new_unique(),
+                addresses: stake_accounts[range].to_vec(),
+            })
+            .collect::<Vec<_>>();
+        let index = lookup_tables::Index::new(luts.clone());
+        let batches = pack(
+            &signer,
+            &mint,
+            stake_accounts.as_slice(),
+            &index,
+            10_000,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            batches
+                .iter()
+                .flat_map(|batch| batch.stake_accounts.clone())
+                .collect::<Vec<_>>(),
+            stake_accounts
+        );
+        assert_eq!(batches[0].lookup_tables, vec![luts[0].clone()]);
+        for batch in batches.iter() {
+            assert!(client::fits_in_transaction(
+                &signer,
+                batch.instructions.as_slice(),
+                batch.lookup_tables.as_slice()
+            )
+            .unwrap());
+            assert!(batch.lookup_tables.len() <= MAX_LOOKUP_TABLES_PER_TX);
+            // only tables holding the batch's stake accounts
+            assert!(batch.lookup_tables.iter().all(|lut| batch
+                .stake_accounts
+                .iter()
+                .any(|account| lut.addresses.contains(account))));
+        }
+        // compute bound
+        let batches = pack(
+            &signer,
+            &mint,
+            stake_accounts.as_slice(),
+            &index,
+            400_000,
+            None,
+        )
+        .unwrap();
+        assert_eq!(batches.len(), 25);
+        assert!(pack(
+            &signer,
+            &mint,
+            stake_accounts.as_slice(),
+            &index,
+            2_000_000,
+            None
+        )
+        .is_err());
+        // batch size bound
+        let batches = pack(
+            &signer,
+            &mint,
+            stake_accounts.as_slice(),
+            &index,
+            10_000,
+            Some(3),
+        )
+        .unwrap();
+        assert_eq!(batches.len(), 25);
+        assert!(batches.iter().all(|batch| batch.instructions.len() <= 3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rebase_all_completes_checkpoint() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        for _ in 0..10 {
+            chain.add_stake(&boost);
+        }
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        rebase_all(
+            &client,
+            &mint,
+            &boost,
+            &stakes,
+            &lookup_tables::Index::default(),
+            &mut Submitter::new(&boost, Strategy::Jito, None),
+        )
+        .await
+        .unwrap();
+        let checkpoint = chain.checkpoint(&boost);
+        assert_eq!(chain.checkpoints_completed(&boost), 1);
+        assert_eq!(checkpoint.current_id, 0);
+        assert_eq!(checkpoint.ts, chain.now());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rebase_all_completes_checkpoint_with_lookup_tables() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        for _ in 0..300 {
+            chain.add_stake(&boost);
+        }
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        let (luts, _) = lookup_tables::sync(&client, &boost, &stakes).await.unwrap();
+        let index = lookup_tables::index(&client, luts.as_slice())
+            .await
+            .unwrap();
+        rebase_all(
+            &client,
+            &mint,
+            &boost,
+            &stakes,
+            &index,
+            &mut Submitter::new(&boost, Strategy::Jito, None),
+        )
+        .await
+        .unwrap();
+        assert_eq!(chain.checkpoints_completed(&boost), 1);
+        assert_eq!(chain.checkpoint(&boost).current_id, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rebase_all_in_dry_run_submits_nothing() {
+        let chain = MockChain::new();
+        let client = chain.dry_run_client();
+        let (mint, boost) = chain.add_boost();
+        for _ in 0..30 {
+            chain.add_stake(&boost);
+        }
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        for strategy in [Strategy::Jito, Strategy::Rpc, Strategy::Helius] {
+            rebase_all(
+                &client,
+                &mint,
+                &boost,
+                &stakes,
+                &lookup_tables::Index::default(),
+                &mut Submitter::new(&boost, strategy, None),
+            )
+            .await
+            .unwrap();
+        }
+        assert_eq!(chain.submissions(), (0, 0));
+        assert_eq!(chain.checkpoints_completed(&boost), 0);
+        assert_eq!(chain.checkpoint(&boost).current_id, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rebase_once_only_when_due() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        for _ in 0..10 {
+            chain.add_stake(&boost);
+        }
+        assert!(rebase_once(&client, &mint).await.unwrap());
+        assert_eq!(chain.checkpoints_completed(&boost), 1);
+        // next checkpoint is not yet due
+        assert!(!rebase_once(&client, &mint).await.unwrap());
+        assert_eq!(chain.checkpoints_completed(&boost), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rebase_all_resumes_after_rolled_back_bundle() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        let stake_accounts = (0..10).map(|_| chain.add_stake(&boost)).collect::<Vec<_>>();
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        chain.roll_back_bundles(1);
+        // resumes from the checkpoint after the rolled back bundle
+        rebase_all(
+            &client,
+            &mint,
+            &boost,
+            &stakes,
+            &lookup_tables::Index::default(),
+            &mut Submitter::new(&boost, Strategy::Jito, None),
+        )
+        .await
+        .unwrap();
+        assert_eq!(chain.checkpoints_completed(&boost), 1);
+        // only the resumed bundle landed, rebasing each stake account once
+        assert_eq!(chain.submissions(), (1, 1));
+        assert!(stake_accounts
+            .iter()
+            .all(|stake| chain.rebases(stake).eq(&1)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rebase_all_picks_up_stakers_missing_from_the_index() {
+        let chain = MockChain::new();
+        let client = chain.subscribed_client();
+        let (mint, boost) = chain.add_boost();
+        for _ in 0..10 {
+            chain.add_stake(&boost);
+        }
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        // joins after the index was built, and is never notified
+        chain.add_stake(&boost);
+        rebase_all(
+            &client,
+            &mint,
+            &boost,
+            &stakes,
+            &lookup_tables::Index::default(),
+            &mut Submitter::new(&boost, Strategy::Jito, None),
+        )
+        .await
+        .unwrap();
+        assert_eq!(chain.checkpoints_completed(&boost), 1);
+        assert_eq!(stakes.stake_accounts(&client).await.unwrap().len(), 11);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rebase_all_resets_empty_checkpoint() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        rebase_all(
+            &client,
+            &mint,
+            &boost,
+            &stakes,
+            &lookup_tables::Index::default(),
+            &mut Submitter::new(&boost, Strategy::Jito, None),
+        )
+        .await
+        .unwrap();
+        assert_eq!(chain.checkpoints_completed(&boost), 1);
+        assert_eq!(chain.submissions(), (1, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn run_completes_checkpoint_cycles() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        let handle = tokio::spawn(async move { run(&client, &mint).await });
+        let cycles = 3;
+        tokio::time::sleep(tokio::time::Duration::from_secs(
+            CHECKPOINT_INTERVAL as u64 * cycles + 60,
+        ))
+        .await;
+        handle.abort();
+        assert!(chain.checkpoints_completed(&boost) > cycles);
+    }
+}
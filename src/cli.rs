@@ -0,0 +1,259 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use ore_boost_api::state::{Boost, Checkpoint};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::checkpoint;
+use crate::client::Client;
+use crate::lookup_tables;
+use crate::stakes::StakeIndex;
+use crate::worker;
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    /// run checkpoint loops for the configured boosts, the default
+    Run,
+    /// print the boost, its checkpoint, time until the next and staker counts
+    Status { mint: Pubkey },
+    /// rebase the checkpoint once if it is due
+    RebaseOnce { mint: Pubkey },
+    /// manage the lookup tables of a boost
+    Luts {
+        #[command(subcommand)]
+        command: LutsCommand,
+    },
+    /// list stake accounts in id order
+    Stakers { mint: Pubkey },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum LutsCommand {
+    /// list registered lookup tables
+    List { mint: Pubkey },
+    /// create and extend lookup tables for new stake accounts
+    Sync { mint: Pubkey },
+    /// deactivate and close obsolete lookup tables
+    Gc { mint: Pubkey },
+}
+
+pub async fn run(client: Arc<Client>, command: Command) -> Result<()> {
+    match command {
+        Command::Run => {
+            // configured mints, or none to run every live boost on chain
+            let mints = client.config.mints()?;
+            worker::run(client, mints).await;
+        }
+        Command::Status { mint } => status(client.as_ref(), &mint).await?.print(),
+        Command::RebaseOnce { mint } => println!("{}", rebase_once(client.as_ref(), &mint).await?),
+        Command::Luts { command } => luts(client.as_ref(), command).await?,
+        Command::Stakers { mint } => stakers(client.as_ref(), &mint).await?,
+    }
+    Ok(())
+}
+
+/// boost and checkpoint as of the status command
+struct Status {
+    boost_pda: Pubkey,
+    boost: Boost,
+    checkpoint_pda: Pubkey,
+    checkpoint: Checkpoint,
+    /// seconds until the next checkpoint, zero if due
+    remaining: u64,
+    stakers: usize,
+    /// stakers rebased so far in the current checkpoint
+    rebased: usize,
+}
+
+impl Status {
+    fn pending(&self) -> usize {
+        self.stakers - self.rebased
+    }
+    fn print(&self) {
+        println!("boost: {}", self.boost_pda);
+        println!("{:#?}", self.boost);
+        println!("checkpoint: {}", self.checkpoint_pda);
+        println!("{:#?}", self.checkpoint);
+        match self.remaining {
+            0 => println!("next checkpoint: due"),
+            secs => println!("next checkpoint: in {} seconds", secs),
+        }
+        println!(
+            "stakers: {} total, {} rebased, {} pending",
+            self.stakers,
+            self.rebased,
+            self.pending()
+        );
+    }
+}
+
+async fn status(client: &Client, mint: &Pubkey) -> Result<Status> {
+    let (boost_pda, _) = ore_boost_api::state::boost_pda(*mint);
+    let (checkpoint_pda, _) = ore_boost_api::state::checkpoint_pda(boost_pda);
+    let boost = client.rpc.get_boost(&boost_pda).await?;
+    let checkpoint = client.rpc.get_checkpoint(&checkpoint_pda).await?;
+    let remaining = checkpoint::time_remaining(client, &checkpoint, &boost_pda).await?;
+    let stake_accounts = client.rpc.get_boost_stake_accounts(&boost_pda).await?;
+    let rebased = stake_accounts
+        .iter()
+        .filter(|(_, stake)| stake.id.lt(&checkpoint.current_id))
+        .count();
+    Ok(Status {
+        boost_pda,
+        boost,
+        checkpoint_pda,
+        checkpoint,
+        remaining,
+        stakers: stake_accounts.len(),
+        rebased,
+    })
+}
+
+async fn rebase_once(client: &Client, mint: &Pubkey) -> Result<&'static str> {
+    if !checkpoint::rebase_once(client, mint).await? {
+        Ok("checkpoint not yet due")
+    } else if client.config.dry_run {
+        Ok("checkpoint simulated")
+    } else {
+        Ok("checkpoint rebased")
+    }
+}
+
+async fn luts(client: &Client, command: LutsCommand) -> Result<()> {
+    match command {
+        LutsCommand::List { mint } => {
+            let (boost_pda, _) = ore_boost_api::state::boost_pda(mint);
+            let listed = lookup_tables::list(client, &boost_pda).await?;
+            for (entry, len) in listed.iter() {
+                let addresses = match len {
+                    Some(len) => format!("{} addresses", len),
+                    None => "missing".to_string(),
+                };
+                println!(
+                    "{} {:?} slot {:?} -- {}",
+                    entry.address, entry.status, entry.slot, addresses
+                );
+            }
+            println!("{} lookup tables", listed.len());
+        }
+        LutsCommand::Sync { mint } => {
+            let (boost_pda, _) = ore_boost_api::state::boost_pda(mint);
+            let stakes = StakeIndex::new(client, &boost_pda).await?;
+            let (luts, stake_accounts) = lookup_tables::sync(client, &boost_pda, &stakes).await?;
+            println!(
+                "{} lookup tables for {} stake accounts",
+                luts.len(),
+                stake_accounts.len()
+            );
+        }
+        LutsCommand::Gc { mint } => {
+            let (boost_pda, _) = ore_boost_api::state::boost_pda(mint);
+            let stake_accounts = client.rpc.get_boost_stake_accounts(&boost_pda).await?;
+            let collected =
+                lookup_tables::gc(client, &boost_pda, stake_accounts.as_slice()).await?;
+            println!(
+                "deactivated {} and closed {} lookup tables, recovered {} SOL",
+                collected.deactivated,
+                collected.closed,
+                collected.lamports as f64 / LAMPORTS_PER_SOL as f64
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn stakers(client: &Client, mint: &Pubkey) -> Result<()> {
+    let (boost_pda, _) = ore_boost_api::state::boost_pda(*mint);
+    let mut stake_accounts = client.rpc.get_boost_stake_accounts(&boost_pda).await?;
+    stake_accounts.sort_by_key(|(_, stake)| stake.id);
+    for (address, stake) in stake_accounts.iter() {
+        println!("{} {} authority {}", stake.id, address, stake.authority);
+    }
+    println!("{} stakers", stake_accounts.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::signer::Signer;
+
+    use super::*;
+    use crate::mock::MockChain;
+
+    #[tokio::test(start_paused = true)]
+    async fn status_counts_rebased_and_pending_stakers() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        let stake_accounts = (0..3).map(|_| chain.add_stake(&boost)).collect::<Vec<_>>();
+        let shown = status(&client, &mint).await.unwrap();
+        assert_eq!(shown.boost_pda, boost);
+        assert_eq!(shown.remaining, 0);
+        assert_eq!((shown.stakers, shown.rebased, shown.pending()), (3, 0, 3));
+        // part way through the checkpoint
+        let ix = ore_boost_api::sdk::rebase(client.keypair.pubkey(), mint, stake_accounts[0]);
+        client.send_transaction(&[ix]).await.unwrap();
+        let shown = status(&client, &mint).await.unwrap();
+        assert_eq!(shown.checkpoint.current_id, 1);
+        assert_eq!((shown.stakers, shown.rebased, shown.pending()), (3, 1, 2));
+        // complete, every staker pending the next
+        rebase_once(&client, &mint).await.unwrap();
+        let shown = status(&client, &mint).await.unwrap();
+        assert!(shown.remaining.gt(&0));
+        assert_eq!((shown.stakers, shown.rebased, shown.pending()), (3, 0, 3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rebase_once_reports_whether_due() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        for _ in 0..3 {
+            chain.add_stake(&boost);
+        }
+        assert_eq!(
+            rebase_once(&client, &mint).await.unwrap(),
+            "checkpoint rebased"
+        );
+        assert_eq!(chain.checkpoints_completed(&boost), 1);
+        assert_eq!(
+            rebase_once(&client, &mint).await.unwrap(),
+            "checkpoint not yet due"
+        );
+        assert_eq!(chain.checkpoints_completed(&boost), 1);
+        // simulated without landing
+        let (mint, boost) = chain.add_boost();
+        chain.add_stake(&boost);
+        let client = chain.dry_run_client();
+        assert_eq!(
+            rebase_once(&client, &mint).await.unwrap(),
+            "checkpoint simulated"
+        );
+        assert_eq!(chain.checkpoints_completed(&boost), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn luts_gc_closes_tables_without_live_stake_accounts() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        let stake_accounts = (0..10).map(|_| chain.add_stake(&boost)).collect::<Vec<_>>();
+        luts(&client, LutsCommand::Sync { mint }).await.unwrap();
+        assert_eq!(chain.lookup_table_count(), 1);
+        // live tables are kept
+        luts(&client, LutsCommand::Gc { mint }).await.unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
+        luts(&client, LutsCommand::Gc { mint }).await.unwrap();
+        assert_eq!(chain.lookup_table_count(), 1);
+        // deactivated once every staker withdraws, then closed
+        for stake in stake_accounts.iter() {
+            chain.close_stake(stake);
+        }
+        luts(&client, LutsCommand::Gc { mint }).await.unwrap();
+        assert_eq!(chain.lookup_table_count(), 1);
+        tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
+        luts(&client, LutsCommand::Gc { mint }).await.unwrap();
+        assert_eq!(chain.lookup_table_count(), 0);
+    }
+}
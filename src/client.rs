@@ -2,325 +2,644 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use helius::types::{
-    Cluster, CreateSmartTransactionConfig, SmartTransaction, SmartTransactionConfig, Timeout,
-};
+use helius::types::{Cluster, SmartTransactionConfig, Timeout};
 use ore_boost_api::state::{Boost, Checkpoint, Stake};
 use solana_account_decoder::UiAccountEncoding;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_client::rpc_config::{
     RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig,
+    RpcSimulateTransactionConfig,
 };
 use solana_client::rpc_filter::{Memcmp, RpcFilterType};
 use solana_sdk::address_lookup_table::state::AddressLookupTable;
-use solana_sdk::address_lookup_table::AddressLookupTableAccount;
+use solana_sdk::address_lookup_table::{self, AddressLookupTableAccount};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::{v0, VersionedMessage};
+use solana_sdk::packet::PACKET_DATA_SIZE;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Signature;
 use solana_sdk::signer::Signer;
+use solana_sdk::transaction::{TransactionError, VersionedTransaction};
 use solana_sdk::{signature::Keypair, signer::EncodableKey};
 use steel::{sysvar, AccountDeserialize, Clock, Discriminator, Instruction};
 
+use crate::config::Config;
 use crate::error::Error::{
-    EmptyJitoBundle, EmptyJitoBundleConfirmation, InvalidHeliusCluster,
-    MissingHeliusSolanaAsyncClient, TooManyTransactionsInJitoBundle, UnconfirmedJitoBundle,
+    InvalidHeliusCluster, MissingHeliusBackend, MissingHeliusSolanaAsyncClient,
+    UnconfirmedJitoBundle, UnconfirmedTransactions,
 };
+use crate::jito;
+use crate::pubsub::{Pubsub, SubscribeClient};
 
 pub struct Client {
-    pub rpc: helius::Helius,
+    pub rpc: Arc<dyn AsyncClient>,
+    pub sender: Arc<dyn SendClient>,
     pub keypair: Arc<Keypair>,
+    pub tipper: Arc<jito::Tipper>,
+    pub config: Arc<Config>,
+    /// sends smart transactions, if connected to helius
+    pub helius: Option<Arc<helius::Helius>>,
+    /// one websocket shared by every subscription, if configured
+    pub pubsub: Option<Arc<dyn SubscribeClient>>,
+    /// slot the last lookup table was derived from,
+    /// held while creating the next
+    pub lookup_table_slot: tokio::sync::Mutex<u64>,
 }
 
 impl Client {
-    pub fn new() -> Result<Self> {
-        let helius_api_key = helius_api_key()?;
-        let helius_cluster = helius_cluster()?;
-        let keypair = keypair()?;
-        let rpc = helius::Helius::new_with_async_solana(helius_api_key.as_str(), helius_cluster)?;
+    /// connects to the configured rpc url if set,
+    /// otherwise to helius with the configured api key and cluster
+    pub fn new(config: Config) -> Result<Self> {
+        let keypair = keypair(config.keypair_path.as_str())?;
+        let (rpc, async_client, helius): (
+            Arc<dyn AsyncClient>,
+            Arc<RpcClient>,
+            Option<Arc<helius::Helius>>,
+        ) = match config.rpc_url.clone() {
+            Some(rpc_url) => {
+                log::info!("using rpc backend: {}", rpc_url);
+                let async_client = Arc::new(RpcClient::new_with_commitment(
+                    rpc_url,
+                    CommitmentConfig::confirmed(),
+                ));
+                let rpc: Arc<dyn AsyncClient> = async_client.clone();
+                (rpc, async_client, None)
+            }
+            None => {
+                log::info!("using helius backend");
+                let helius_api_key = config.helius_api_key.clone().unwrap_or_default();
+                let helius_cluster = helius_cluster(config.helius_cluster.as_deref())?;
+                let helius =
+                    helius::Helius::new_with_async_solana(helius_api_key.as_str(), helius_cluster)?;
+                let async_client = helius
+                    .async_rpc_client
+                    .clone()
+                    .ok_or(MissingHeliusSolanaAsyncClient)?;
+                let helius = Arc::new(helius);
+                let rpc: Arc<dyn AsyncClient> = helius.clone();
+                (rpc, async_client, Some(helius))
+            }
+        };
+        let pubsub = config.ws_url.clone().map(|ws_url| {
+            let pubsub: Arc<dyn SubscribeClient> = Arc::new(Pubsub::new(ws_url));
+            pubsub
+        });
         let client = Self {
             rpc,
+            sender: Arc::new(RpcSender::new(
+                async_client,
+                jito::BlockEngines::new(config.jito.block_engines.clone())?,
+            )),
             keypair: Arc::new(keypair),
+            tipper: Arc::new(jito::Tipper::new(
+                config.jito.tip.clone(),
+                config.boost_tips()?,
+            )),
+            config: Arc::new(config),
+            helius,
+            pubsub,
+            lookup_table_slot: tokio::sync::Mutex::new(0),
         };
         Ok(client)
     }
     pub async fn send_transaction(&self, ixs: &[Instruction]) -> Result<Signature> {
-        let signer = Arc::clone(&self.keypair);
-        let signers: Vec<Arc<dyn Signer>> = vec![signer];
-        let tx = SmartTransactionConfig::new(ixs.to_vec(), signers, Timeout::default());
-        let sig = self.rpc.send_smart_transaction(tx).await?;
-        Ok(sig)
-    }
-    #[allow(dead_code)]
-    pub async fn send_transaction_with_luts(
-        &self,
-        ixs: &[Instruction],
-        luts: &[Pubkey],
-    ) -> Result<Signature> {
-        let signer = Arc::clone(&self.keypair);
-        let signers: Vec<Arc<dyn Signer>> = vec![signer];
-        let lookup_tables = self.rpc.get_lookup_tables(luts).await?;
-        let tx = CreateSmartTransactionConfig {
-            instructions: ixs.to_vec(),
-            signers,
-            lookup_tables: Some(lookup_tables),
-            fee_payer: None,
-            priority_fee_cap: None,
-        };
-        let tx = SmartTransactionConfig {
-            create_config: tx,
-            send_options: RpcSendTransactionConfig::default(),
-            timeout: Timeout::default(),
-        };
-        let sig = self.rpc.send_smart_transaction(tx).await?;
+        let tx = self.create_transaction(ixs).await?;
+        if self.config.dry_run {
+            self.dry_run(std::slice::from_ref(&tx), 0).await?;
+            return Ok(tx.signatures[0]);
+        }
+        let sig = self.sender.send_transaction(&tx).await?;
         Ok(sig)
     }
-    /// returns bundle-id if confirmed
-    pub async fn send_jito_bundle_with_luts(
+    /// returns signatures once every transaction is confirmed
+    ///
+    /// sent one at a time as helius smart transactions,
+    /// each simulated for compute and priced from the helius fee estimate,
+    /// then confirmed before the next is built against the landed state.
+    /// in a dry run, each is compiled with the max compute unit limit and simulated instead
+    pub async fn send_smart_transactions_with_luts(
         &self,
-        ixs: &[&[Instruction]],
-        luts: &[Pubkey],
-    ) -> Result<String> {
-        let jito_api_url = "https://mainnet.block-engine.jito.wtf/api/v1/bundles";
-        if ixs.len().gt(&5) {
-            return Err(anyhow::anyhow!(TooManyTransactionsInJitoBundle));
-        }
-        if ixs.is_empty() {
-            return Err(anyhow::anyhow!(EmptyJitoBundle));
-        }
-        let mut transactions = vec![];
-        for (index, slice) in ixs.iter().enumerate() {
-            let tx = if index.eq(&(ixs.len() - 1)) {
-                // last of n transactions in bundle, add tip
-                self.create_jito_transaction_with_luts(slice, luts).await?
-            } else {
-                self.create_transaction_with_luts(slice, luts).await?
-            };
-            transactions.push(tx);
-        }
-        let bundle_id = self
-            .rpc
-            .send_jito_bundle(transactions, jito_api_url)
+        ixs: &[(&[Instruction], &[Pubkey])],
+    ) -> Result<Vec<Signature>> {
+        let luts = ixs.iter().map(|(_, luts)| *luts).collect::<Vec<_>>();
+        let lookup_tables = self
+            .get_lookup_tables_per_transaction(luts.as_slice())
             .await?;
-        log::info!("bundle id: {:?}", bundle_id);
-        self.confirm_jito_bundle(bundle_id.as_str()).await?;
-        Ok(bundle_id)
-    }
-    /// returns ok if confirmed
-    pub async fn send_jito_bundle(&self, ixs: &[&[Instruction]]) -> Result<()> {
-        let jito_api_url = "https://mainnet.block-engine.jito.wtf/api/v1/bundles";
-        if ixs.len().gt(&5) {
-            return Err(anyhow::anyhow!(TooManyTransactionsInJitoBundle));
+        if self.config.dry_run {
+            let blockhash = self.rpc.get_latest_blockhash().await?;
+            let mut transactions = vec![];
+            for ((slice, _), lookup_tables) in ixs.iter().zip(lookup_tables.iter()) {
+                let mut budgeted = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+                    MAX_COMPUTE_UNITS,
+                )];
+                budgeted.extend_from_slice(slice);
+                let tx = self.compile_transaction(
+                    budgeted.as_slice(),
+                    lookup_tables.as_slice(),
+                    blockhash,
+                )?;
+                transactions.push(tx);
+            }
+            self.dry_run(transactions.as_slice(), 0).await?;
+            return Ok(transactions.iter().map(|tx| tx.signatures[0]).collect());
         }
-        if ixs.is_empty() {
-            return Err(anyhow::anyhow!(EmptyJitoBundle));
+        let helius = self.helius.as_ref().ok_or(MissingHeliusBackend)?;
+        let signer: Arc<dyn Signer> = self.keypair.clone();
+        let mut sigs = vec![];
+        for ((slice, _), lookup_tables) in ixs.iter().zip(lookup_tables) {
+            let mut config = SmartTransactionConfig::new(
+                slice.to_vec(),
+                vec![signer.clone()],
+                Timeout::default(),
+            );
+            config.create_config.lookup_tables = Some(lookup_tables);
+            let sig = helius.send_smart_transaction(config).await?;
+            sigs.push(sig);
         }
-        let mut transactions = vec![];
-        for (index, slice) in ixs.iter().enumerate() {
-            let tx = if index.eq(&(ixs.len() - 1)) {
-                // last of n transactions in bundle, add tip
-                self.create_jito_transaction(slice).await?
-            } else {
-                self.create_transaction(slice).await?
+        Ok(sigs)
+    }
+    /// simulate each transaction instead of sending it,
+    /// reporting what it would have cost
+    ///
+    /// each is simulated against the current state,
+    /// so a transaction that depends on an earlier one landing
+    /// reports the error it would hit without it.
+    /// the tip is paid by the last transaction.
+    pub async fn dry_run(
+        &self,
+        transactions: &[VersionedTransaction],
+        tip: u64,
+    ) -> Result<Vec<DryRun>> {
+        let last = transactions.len().saturating_sub(1);
+        let mut reports = vec![];
+        for (index, tx) in transactions.iter().enumerate() {
+            let simulation = self.rpc.simulate_transaction(tx).await?;
+            let lookups = tx
+                .message
+                .address_table_lookups()
+                .unwrap_or_default()
+                .iter()
+                .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+                .sum::<usize>();
+            let report = DryRun {
+                accounts: tx.message.static_account_keys().len() + lookups,
+                size: bincode::serialized_size(tx)? as usize,
+                units_consumed: simulation.units_consumed,
+                err: simulation.err,
+                tip: if index.eq(&last) { tip } else { 0 },
+                fee: expected_fee(&tx.message),
             };
-            transactions.push(tx);
+            log::info!(
+                "dry run -- transaction {} of {}: {} accounts, {} bytes, {:?} compute units, {} lamports fee, {} lamports tip",
+                index + 1,
+                transactions.len(),
+                report.accounts,
+                report.size,
+                report.units_consumed,
+                report.fee,
+                report.tip
+            );
+            if let Some(err) = report.err.as_ref() {
+                log::warn!("dry run -- simulation failed: {:?}", err);
+                log::warn!("dry run -- simulation logs: {:?}", simulation.logs);
+            }
+            reports.push(report);
         }
-        let bundle_id = self
-            .rpc
-            .send_jito_bundle(transactions, jito_api_url)
+        Ok(reports)
+    }
+    /// sign a bundle to be sent later,
+    /// each transaction compiled against its own lookup tables
+    ///
+    /// fetches the lookup tables and a blockhash once for the whole bundle
+    pub async fn build_jito_bundle_with_luts(
+        &self,
+        boost: &Pubkey,
+        ixs: &[(&[Instruction], &[Pubkey])],
+    ) -> Result<jito::Bundle> {
+        let tip = self.tipper.tip(boost).await?;
+        let built = tokio::time::Instant::now();
+        let luts = ixs.iter().map(|(_, luts)| *luts).collect::<Vec<_>>();
+        let lookup_tables = self
+            .get_lookup_tables_per_transaction(luts.as_slice())
             .await?;
-        log::info!("bundle id: {:?}", bundle_id);
-        self.confirm_jito_bundle(bundle_id.as_str()).await?;
-        Ok(())
+        let blockhash = self.rpc.get_latest_blockhash().await?;
+        let transactions = self.compile_jito_bundle(
+            ixs.iter().map(|(slice, _)| *slice),
+            lookup_tables.as_slice(),
+            blockhash,
+            tip.lamports,
+        )?;
+        Ok(jito::Bundle {
+            transactions,
+            tip,
+            built,
+        })
     }
-    async fn confirm_jito_bundle(&self, bundle_id: &str) -> Result<()> {
-        let mut retries = 0;
-        let max_retires = 15;
-        loop {
-            match self
-                .request_confirm_jito_bundle_inflight(bundle_id.to_string())
-                .await
-            {
-                Ok(()) => {
-                    return Ok(());
-                }
-                Err(err) => {
-                    log::error!("{:?}", err);
-                    retries += 1;
-                    if retries == max_retires {
-                        return Err(UnconfirmedJitoBundle).map_err(From::from);
-                    }
-                    tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-                }
-            }
-        }
+    /// returns bundle-id and outcome once tracked
+    ///
+    /// consumes the bundle, settling its reserved tip
+    pub async fn send_built_jito_bundle(&self, bundle: jito::Bundle) -> Result<jito::Tracked> {
+        let tracked = self
+            .sender
+            .send_bundle(bundle.transactions.as_slice())
+            .await?;
+        self.tipper.report(bundle.tip, &tracked.outcome);
+        Ok(tracked)
     }
-    async fn request_confirm_jito_bundle_inflight(&self, bundle_id: String) -> Result<()> {
-        let jito_api_url = "https://mainnet.block-engine.jito.wtf/api/v1/getInflightBundleStatuses";
-        let parsed_url = url::Url::parse(jito_api_url)?;
-        #[derive(serde::Serialize, Debug)]
-        pub struct BasicRequest {
-            pub jsonrpc: String,
-            pub id: u32,
-            pub method: String,
-            pub params: Vec<Vec<String>>,
-        }
-        let request: BasicRequest = BasicRequest {
-            jsonrpc: "2.0".to_string(),
-            id: 1,
-            method: "getInflightBundleStatuses".to_string(),
-            params: vec![vec![bundle_id.to_string()]],
+    /// returns signatures once every transaction is confirmed
+    ///
+    /// sent in order with priority fees, then confirmed together.
+    /// compute is sized by simulating the first transaction,
+    /// since the rest depend on it landing.
+    pub async fn send_transactions_with_luts(
+        &self,
+        ixs: &[(&[Instruction], &[Pubkey])],
+    ) -> Result<Vec<Signature>> {
+        let Some((first, _)) = ixs.first() else {
+            return Ok(vec![]);
         };
-        let response: serde_json::Value = self
+        let luts = ixs.iter().map(|(_, luts)| *luts).collect::<Vec<_>>();
+        let lookup_tables = self
+            .get_lookup_tables_per_transaction(luts.as_slice())
+            .await?;
+        let blockhash = self.rpc.get_latest_blockhash().await?;
+        let units = self
+            .simulate_compute_units(first, lookup_tables[0].as_slice(), blockhash)
+            .await?;
+        let units_per_ix = units.div_ceil(first.len().max(1) as u64);
+        // price against the writable accounts
+        let writable = ixs
+            .iter()
+            .flat_map(|(slice, _)| slice.iter())
+            .flat_map(|ix| ix.accounts.iter())
+            .filter(|meta| meta.is_writable)
+            .map(|meta| meta.pubkey)
+            .collect::<Vec<_>>();
+        let priority_fee = self
             .rpc
-            .client
-            .post(parsed_url)
-            .json(&request)
-            .send()
-            .await
-            .map_err(|err| anyhow::anyhow!(err))?
-            .json::<serde_json::Value>()
+            .get_recent_priority_fee(writable.as_slice())
             .await?;
-        if let Some(error) = response.clone().get("error") {
-            return Err(anyhow::anyhow!(error.to_string())).map_err(From::from);
-        }
-        #[derive(serde::Deserialize, Debug)]
-        struct Inner {
-            status: String,
+        let mut transactions = vec![];
+        for ((slice, _), lookup_tables) in ixs.iter().zip(lookup_tables.iter()) {
+            let units = units_per_ix * slice.len() as u64;
+            let units = (units + units / 10).clamp(1_000, MAX_COMPUTE_UNITS as u64) as u32;
+            let mut budgeted = vec![
+                ComputeBudgetInstruction::set_compute_unit_limit(units),
+                ComputeBudgetInstruction::set_compute_unit_price(priority_fee),
+            ];
+            budgeted.extend_from_slice(slice);
+            let tx =
+                self.compile_transaction(budgeted.as_slice(), lookup_tables.as_slice(), blockhash)?;
+            transactions.push(tx);
         }
-        #[derive(serde::Deserialize, Debug)]
-        struct Middle {
-            value: Vec<Inner>,
+        if self.config.dry_run {
+            self.dry_run(transactions.as_slice(), 0).await?;
+            return Ok(transactions.iter().map(|tx| tx.signatures[0]).collect());
         }
-        #[derive(serde::Deserialize, Debug)]
-        struct Outer {
-            result: Middle,
+        self.sender.send_transactions(transactions.as_slice()).await
+    }
+    /// returns ok if confirmed
+    pub async fn send_jito_bundle(&self, boost: &Pubkey, ixs: &[&[Instruction]]) -> Result<()> {
+        let tip = self.tipper.tip(boost).await?;
+        let blockhash = self.rpc.get_latest_blockhash().await?;
+        let lookup_tables = vec![vec![]; ixs.len()];
+        let transactions = self.compile_jito_bundle(
+            ixs.iter().copied(),
+            lookup_tables.as_slice(),
+            blockhash,
+            tip.lamports,
+        )?;
+        if self.config.dry_run {
+            self.dry_run(transactions.as_slice(), tip.lamports).await?;
+            return Ok(());
         }
-        let response = serde_json::from_value(response)?;
-        let response: Outer = serde_json::from_value(response)?;
-        let first = response
-            .result
-            .value
-            .first()
-            .ok_or(anyhow::anyhow!(EmptyJitoBundleConfirmation))?;
-        match first.status.as_str() {
-            "Landed" => {
-                log::info!("jito confirmation: {:?}", response);
-                Ok(())
-            }
-            status => {
-                log::info!("bundle status: {}", status);
-                Err(anyhow::anyhow!(UnconfirmedJitoBundle))
-            }
+        let tracked = self.sender.send_bundle(transactions.as_slice()).await?;
+        self.tipper.report(tip, &tracked.outcome);
+        match tracked.outcome {
+            jito::Outcome::Landed { .. } => Ok(()),
+            _ => Err(anyhow::anyhow!(UnconfirmedJitoBundle)),
         }
     }
-    /// returns base58 encoded transaction string
-    async fn create_jito_transaction(&self, ixs: &[Instruction]) -> Result<String> {
-        let signer = Arc::clone(&self.keypair);
-        let signers: Vec<Arc<dyn Signer>> = vec![signer];
-        let config = CreateSmartTransactionConfig::new(ixs.to_vec(), signers);
-        let (tx, _) = self
+    /// prepends compute budget instructions,
+    /// sizing the compute unit limit from simulation
+    async fn create_transaction(&self, ixs: &[Instruction]) -> Result<VersionedTransaction> {
+        let blockhash = self.rpc.get_latest_blockhash().await?;
+        let units = self.simulate_compute_units(ixs, &[], blockhash).await?;
+        let units = (units + units / 10).clamp(1_000, MAX_COMPUTE_UNITS as u64) as u32;
+        // price against the writable accounts
+        let writable = ixs
+            .iter()
+            .flat_map(|ix| ix.accounts.iter())
+            .filter(|meta| meta.is_writable)
+            .map(|meta| meta.pubkey)
+            .collect::<Vec<_>>();
+        let priority_fee = self
             .rpc
-            .create_smart_transaction_with_tip(config, Some(100_000))
+            .get_recent_priority_fee(writable.as_slice())
             .await?;
-        Ok(tx)
+        let mut budgeted = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(units),
+            ComputeBudgetInstruction::set_compute_unit_price(priority_fee),
+        ];
+        budgeted.extend_from_slice(ixs);
+        self.compile_transaction(budgeted.as_slice(), &[], blockhash)
     }
-    /// returns base58 encoded transaction string
-    async fn create_transaction(&self, ixs: &[Instruction]) -> Result<String> {
-        let signer = Arc::clone(&self.keypair);
-        let signers: Vec<Arc<dyn Signer>> = vec![signer];
-        let config = CreateSmartTransactionConfig::new(ixs.to_vec(), signers);
-        let (tx, _) = self.rpc.create_smart_transaction(&config).await?;
-        let bytes = match tx {
-            SmartTransaction::Legacy(tx) => bincode::serialize(&tx)?,
-            SmartTransaction::Versioned(tx) => bincode::serialize(&tx)?,
-        };
-        let string = solana_sdk::bs58::encode(bytes).into_string();
-        Ok(string)
+    /// compute units consumed by the instructions, from simulation
+    pub async fn estimate_compute_units(&self, ixs: &[Instruction]) -> Result<u64> {
+        let blockhash = self.rpc.get_latest_blockhash().await?;
+        self.simulate_compute_units(ixs, &[], blockhash).await
     }
-    async fn create_transaction_with_luts(
+    /// simulate with max compute units
+    async fn simulate_compute_units(
         &self,
         ixs: &[Instruction],
-        luts: &[Pubkey],
-    ) -> Result<String> {
-        let signer = Arc::clone(&self.keypair);
-        let signers: Vec<Arc<dyn Signer>> = vec![signer];
-        let lookup_tables = self.rpc.get_lookup_tables(luts).await?;
-        let config = CreateSmartTransactionConfig {
-            instructions: ixs.to_vec(),
-            signers,
-            lookup_tables: Some(lookup_tables),
-            fee_payer: None,
-            priority_fee_cap: None,
-        };
-        let (tx, _) = self.rpc.create_smart_transaction(&config).await?;
-        let bytes = match tx {
-            SmartTransaction::Legacy(tx) => bincode::serialize(&tx)?,
-            SmartTransaction::Versioned(tx) => bincode::serialize(&tx)?,
-        };
-        let string = solana_sdk::bs58::encode(bytes).into_string();
-        Ok(string)
+        lookup_tables: &[AddressLookupTableAccount],
+        blockhash: Hash,
+    ) -> Result<u64> {
+        let mut budgeted = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+            MAX_COMPUTE_UNITS,
+        )];
+        budgeted.extend_from_slice(ixs);
+        let tx = self.compile_transaction(budgeted.as_slice(), lookup_tables, blockhash)?;
+        let simulation = self.rpc.simulate_transaction(&tx).await?;
+        if let Some(err) = simulation.err {
+            // carry on with a default budget,
+            // so every transaction of the dry run is simulated and reported
+            if self.config.dry_run {
+                log::warn!("dry run -- sizing simulation failed: {:?}", err);
+                return Ok((DEFAULT_UNITS_PER_IX * ixs.len() as u64).min(MAX_COMPUTE_UNITS as u64));
+            }
+            log::error!("simulation logs: {:?}", simulation.logs);
+            return Err(anyhow::anyhow!(err));
+        }
+        let units = simulation
+            .units_consumed
+            .unwrap_or(MAX_COMPUTE_UNITS as u64);
+        Ok(units)
     }
-    async fn create_jito_transaction_with_luts(
+    /// compile each transaction against its lookup tables,
+    /// appending the jito tip to the last
+    fn compile_jito_bundle<'a>(
+        &self,
+        ixs: impl ExactSizeIterator<Item = &'a [Instruction]>,
+        lookup_tables: &[Vec<AddressLookupTableAccount>],
+        blockhash: Hash,
+        tip: u64,
+    ) -> Result<Vec<VersionedTransaction>> {
+        let last = ixs.len().saturating_sub(1);
+        let mut transactions = vec![];
+        for (index, (slice, lookup_tables)) in ixs.zip(lookup_tables.iter()).enumerate() {
+            let tx = if index.eq(&last) {
+                // last of n transactions in bundle, add tip
+                let mut tipped = slice.to_vec();
+                tipped.push(jito::tip_instruction(&self.keypair.pubkey(), tip)?);
+                self.compile_transaction(tipped.as_slice(), lookup_tables.as_slice(), blockhash)?
+            } else {
+                self.compile_transaction(slice, lookup_tables.as_slice(), blockhash)?
+            };
+            transactions.push(tx);
+        }
+        Ok(transactions)
+    }
+    /// fetch every distinct lookup table once,
+    /// returns the tables of each transaction in order
+    async fn get_lookup_tables_per_transaction(
+        &self,
+        luts: &[&[Pubkey]],
+    ) -> Result<Vec<Vec<AddressLookupTableAccount>>> {
+        let mut distinct = luts
+            .iter()
+            .flat_map(|luts| luts.iter())
+            .copied()
+            .collect::<Vec<_>>();
+        distinct.sort_unstable();
+        distinct.dedup();
+        let fetched = self.rpc.get_lookup_tables(distinct.as_slice()).await?;
+        let per_transaction = luts
+            .iter()
+            .map(|luts| {
+                luts.iter()
+                    .filter_map(|lut| fetched.iter().find(|table| table.key.eq(lut)))
+                    .cloned()
+                    .collect()
+            })
+            .collect();
+        Ok(per_transaction)
+    }
+    /// compile v0 message and sign with the worker keypair
+    fn compile_transaction(
         &self,
         ixs: &[Instruction],
-        luts: &[Pubkey],
-    ) -> Result<String> {
-        let signer = Arc::clone(&self.keypair);
-        let signers: Vec<Arc<dyn Signer>> = vec![signer];
-        let lookup_tables = self.rpc.get_lookup_tables(luts).await?;
-        let config = CreateSmartTransactionConfig {
-            instructions: ixs.to_vec(),
-            signers,
-            lookup_tables: Some(lookup_tables),
-            fee_payer: None,
-            priority_fee_cap: None,
-        };
-        let (tx, _) = self
-            .rpc
-            .create_smart_transaction_with_tip(config, Some(100_000))
-            .await?;
+        lookup_tables: &[AddressLookupTableAccount],
+        blockhash: Hash,
+    ) -> Result<VersionedTransaction> {
+        let message =
+            v0::Message::try_compile(&self.keypair.pubkey(), ixs, lookup_tables, blockhash)?;
+        let tx =
+            VersionedTransaction::try_new(VersionedMessage::V0(message), &[self.keypair.as_ref()])?;
         Ok(tx)
     }
 }
 
+pub const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+
+/// signature status polls, two seconds apart,
+/// about as long as a blockhash is valid
+const MAX_CONFIRMATION_RETRIES: u32 = 45;
+
+/// whether the instructions fit in one transaction under the packet size limit,
+/// leaving room for the compute budget instructions and the jito tip
+pub fn fits_in_transaction(
+    payer: &Pubkey,
+    ixs: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+) -> Result<bool> {
+    let mut padded = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNITS),
+        ComputeBudgetInstruction::set_compute_unit_price(0),
+    ];
+    padded.extend_from_slice(ixs);
+    padded.push(jito::tip_instruction(payer, jito::TIP_LAMPORTS)?);
+    // too many accounts to index
+    let Ok(message) =
+        v0::Message::try_compile(payer, padded.as_slice(), lookup_tables, Hash::default())
+    else {
+        return Ok(false);
+    };
+    let tx = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header.num_required_signatures as usize],
+        message: VersionedMessage::V0(message),
+    };
+    let size = bincode::serialized_size(&tx)? as usize;
+    Ok(size.le(&PACKET_DATA_SIZE))
+}
+
+/// base fee per signature, in lamports
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// compute units per instruction without a compute unit limit
+const DEFAULT_UNITS_PER_IX: u64 = 200_000;
+
+/// what a transaction would have cost, from simulation
+#[derive(Debug)]
+pub struct DryRun {
+    /// static and looked up accounts
+    pub accounts: usize,
+    /// serialized bytes
+    pub size: usize,
+    pub units_consumed: Option<u64>,
+    pub err: Option<TransactionError>,
+    /// lamports
+    pub tip: u64,
+    /// base and priority fee in lamports, excluding the tip
+    pub fee: u64,
+}
+
+/// base fee per signature,
+/// plus the priority fee set by the compute budget instructions
+fn expected_fee(message: &VersionedMessage) -> u64 {
+    let keys = message.static_account_keys();
+    let mut limit = None;
+    let mut price = 0;
+    for ix in message.instructions() {
+        if keys
+            .get(ix.program_id_index as usize)
+            .is_none_or(|program| program.ne(&solana_sdk::compute_budget::ID))
+        {
+            continue;
+        }
+        // borsh encoded, a one byte tag then the little endian value
+        match ix.data.split_first() {
+            Some((&2, value)) => {
+                limit = <[u8; 4]>::try_from(value)
+                    .ok()
+                    .map(|value| u32::from_le_bytes(value) as u64);
+            }
+            Some((&3, value)) => {
+                price = <[u8; 8]>::try_from(value)
+                    .map(u64::from_le_bytes)
+                    .unwrap_or_default();
+            }
+            _ => {}
+        }
+    }
+    let limit = limit.unwrap_or_else(|| {
+        (DEFAULT_UNITS_PER_IX * message.instructions().len() as u64).min(MAX_COMPUTE_UNITS as u64)
+    });
+    let priority_fee = (price as u128 * limit as u128).div_ceil(1_000_000) as u64;
+    let signatures = message.header().num_required_signatures as u64;
+    LAMPORTS_PER_SIGNATURE * signatures + priority_fee
+}
+
+/// transaction simulation result
+#[derive(Debug)]
+pub struct Simulation {
+    pub err: Option<TransactionError>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+}
+
+/// submits signed transactions
 #[async_trait]
-pub trait AsyncClient {
-    fn get_async_client(&self) -> Result<Arc<RpcClient>>;
-    async fn get_boost(&self, boost: &Pubkey) -> Result<Boost>;
-    async fn get_boost_stake_accounts(&self, boost: &Pubkey) -> Result<Vec<(Pubkey, Stake)>>;
-    async fn get_checkpoint(&self, checkpoint: &Pubkey) -> Result<Checkpoint>;
-    async fn get_clock(&self) -> Result<Clock>;
-    async fn get_lookup_table(&self, lut: &Pubkey) -> Result<AddressLookupTableAccount>;
-    async fn get_lookup_tables(&self, luts: &[Pubkey]) -> Result<Vec<AddressLookupTableAccount>>;
+pub trait SendClient: Send + Sync {
+    /// returns signature if confirmed
+    async fn send_transaction(&self, tx: &VersionedTransaction) -> Result<Signature>;
+    /// sends every transaction in order without preflight,
+    /// returns signatures once all are confirmed
+    async fn send_transactions(&self, txs: &[VersionedTransaction]) -> Result<Vec<Signature>>;
+    /// returns bundle-id and outcome once tracked
+    async fn send_bundle(&self, txs: &[VersionedTransaction]) -> Result<jito::Tracked>;
+}
+
+/// sends transactions over any rpc
+/// and bundles over the jito block engine
+pub struct RpcSender {
+    rpc: Arc<RpcClient>,
+    jito: jito::BlockEngines,
+}
+
+impl RpcSender {
+    pub fn new(rpc: Arc<RpcClient>, jito: jito::BlockEngines) -> Self {
+        Self { rpc, jito }
+    }
 }
 
 #[async_trait]
-impl AsyncClient for helius::Helius {
-    fn get_async_client(&self) -> Result<Arc<RpcClient>> {
-        let res = match &self.async_rpc_client {
-            Some(rpc) => {
-                let rpc = Arc::clone(rpc);
-                Ok(rpc)
-            }
-            None => Err(MissingHeliusSolanaAsyncClient),
+impl SendClient for RpcSender {
+    async fn send_transaction(&self, tx: &VersionedTransaction) -> Result<Signature> {
+        let sig = self.rpc.send_and_confirm_transaction(tx).await?;
+        Ok(sig)
+    }
+    async fn send_transactions(&self, txs: &[VersionedTransaction]) -> Result<Vec<Signature>> {
+        // later transactions depend on earlier ones landing
+        let config = RpcSendTransactionConfig {
+            skip_preflight: true,
+            ..Default::default()
         };
-        res.map_err(From::from)
+        let mut sigs = vec![];
+        for tx in txs {
+            let sig = self.rpc.send_transaction_with_config(tx, config).await?;
+            sigs.push(sig);
+        }
+        // confirm together
+        let mut retries = 0;
+        loop {
+            let statuses = self
+                .rpc
+                .get_signature_statuses(sigs.as_slice())
+                .await?
+                .value;
+            for status in statuses.iter().flatten() {
+                if let Some(err) = status.err.clone() {
+                    return Err(anyhow::anyhow!(err));
+                }
+            }
+            if statuses.iter().all(|status| {
+                status
+                    .as_ref()
+                    .is_some_and(|status| status.satisfies_commitment(self.rpc.commitment()))
+            }) {
+                return Ok(sigs);
+            }
+            retries += 1;
+            if retries == MAX_CONFIRMATION_RETRIES {
+                return Err(anyhow::anyhow!(UnconfirmedTransactions));
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        }
     }
+    async fn send_bundle(&self, txs: &[VersionedTransaction]) -> Result<jito::Tracked> {
+        self.jito.send_bundle(txs).await
+    }
+}
+
+/// reads chain state
+///
+/// every method defaults to the solana rpc client
+/// returned by `get_async_client`
+#[async_trait]
+pub trait AsyncClient: Send + Sync {
+    fn get_async_client(&self) -> Result<&RpcClient>;
     async fn get_boost(&self, boost: &Pubkey) -> Result<Boost> {
         let data = self.get_async_client()?.get_account_data(boost).await?;
         let boost = Boost::try_from_bytes(data.as_slice())?;
         Ok(*boost)
     }
+    async fn get_boosts(&self) -> Result<Vec<(Pubkey, Boost)>> {
+        let accounts =
+            get_program_accounts::<Boost>(self.get_async_client()?, &ore_boost_api::ID, vec![])
+                .await?;
+        Ok(accounts)
+    }
     async fn get_boost_stake_accounts(&self, boost: &Pubkey) -> Result<Vec<(Pubkey, Stake)>> {
         let accounts = get_program_accounts::<Stake>(
-            self.get_async_client()?.as_ref(),
+            self.get_async_client()?,
             &ore_boost_api::ID,
-            vec![],
+            vec![(STAKE_BOOST_OFFSET, boost.to_bytes().to_vec())],
         )
         .await?;
-        let accounts = accounts
-            .into_iter()
-            .filter(|(_, stake)| stake.boost.eq(boost))
-            .collect();
         Ok(accounts)
     }
     async fn get_checkpoint(&self, checkpoint: &Pubkey) -> Result<Checkpoint> {
@@ -339,6 +658,21 @@ impl AsyncClient for helius::Helius {
         let clock = bincode::deserialize::<Clock>(data.as_slice())?;
         Ok(clock)
     }
+    /// whether every signature landed without error
+    /// at the client's commitment, false if there are none to check
+    async fn signatures_confirmed(&self, sigs: &[Signature]) -> Result<bool> {
+        if sigs.is_empty() {
+            return Ok(false);
+        }
+        let client = self.get_async_client()?;
+        let statuses = client.get_signature_statuses(sigs).await?.value;
+        let confirmed = statuses.iter().all(|status| {
+            status.as_ref().is_some_and(|status| {
+                status.err.is_none() && status.satisfies_commitment(client.commitment())
+            })
+        });
+        Ok(confirmed)
+    }
     async fn get_lookup_table(&self, lut: &Pubkey) -> Result<AddressLookupTableAccount> {
         let rpc = self.get_async_client()?;
         let data = rpc.get_account_data(lut).await?;
@@ -349,45 +683,211 @@ impl AsyncClient for helius::Helius {
         };
         Ok(account)
     }
-    async fn get_lookup_tables(&self, luts: &[Pubkey]) -> Result<Vec<AddressLookupTableAccount>> {
-        // need address for each account so fetch sequentially
-        // get multiple accounts does not return the respective pubkeys
+    /// lookup tables in request order, none if missing or closed
+    ///
+    /// get multiple accounts returns accounts in request order,
+    /// so each result is zipped with its address
+    async fn get_multiple_lookup_tables(
+        &self,
+        luts: &[Pubkey],
+    ) -> Result<Vec<Option<AddressLookupTableAccount>>> {
+        let rpc = self.get_async_client()?;
         let mut accounts = vec![];
-        for lut in luts {
-            let account = self.get_lookup_table(lut).await?;
-            accounts.push(account);
+        for chunk in luts.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let results = rpc.get_multiple_accounts(chunk).await?;
+            for (lut, account) in chunk.iter().zip(results) {
+                let account = account
+                    .and_then(|account| AddressLookupTable::deserialize(&account.data).ok())
+                    .map(|table| AddressLookupTableAccount {
+                        key: *lut,
+                        addresses: table.addresses.to_vec(),
+                    });
+                accounts.push(account);
+            }
         }
         Ok(accounts)
     }
+    /// lookup tables in request order, skipping any missing or closed
+    async fn get_lookup_tables(&self, luts: &[Pubkey]) -> Result<Vec<AddressLookupTableAccount>> {
+        let accounts = self.get_multiple_lookup_tables(luts).await?;
+        let mut found = vec![];
+        for (lut, account) in luts.iter().zip(accounts) {
+            match account {
+                Some(account) => found.push(account),
+                None => log::warn!("lookup table {} is missing or closed", lut),
+            }
+        }
+        Ok(found)
+    }
+    /// lookup tables whose authority is the given pubkey,
+    /// each with its deactivation slot if deactivated
+    async fn get_lookup_tables_by_authority(
+        &self,
+        authority: &Pubkey,
+    ) -> Result<Vec<(AddressLookupTableAccount, Option<u64>)>> {
+        let filters = vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            LOOKUP_TABLE_AUTHORITY_OFFSET,
+            authority.to_bytes().to_vec(),
+        ))];
+        let result = self
+            .get_async_client()?
+            .get_program_accounts_with_config(
+                &address_lookup_table::program::ID,
+                RpcProgramAccountsConfig {
+                    filters: Some(filters),
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )
+            .await?;
+        let accounts = result
+            .into_iter()
+            .flat_map(|(pubkey, account)| {
+                let table = AddressLookupTable::deserialize(account.data.as_slice())?;
+                Ok::<_, anyhow::Error>((pubkey, table.meta, table.addresses.to_vec()))
+            })
+            .map(|(key, meta, addresses)| {
+                let deactivation_slot =
+                    Some(meta.deactivation_slot).filter(|slot| slot.ne(&u64::MAX));
+                (
+                    AddressLookupTableAccount { key, addresses },
+                    deactivation_slot,
+                )
+            })
+            .collect();
+        Ok(accounts)
+    }
+    /// whether each account exists, in request order
+    async fn accounts_exist(&self, addresses: &[Pubkey]) -> Result<Vec<bool>> {
+        let rpc = self.get_async_client()?;
+        let mut exist = vec![];
+        for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let accounts = rpc.get_multiple_accounts(chunk).await?;
+            exist.extend(accounts.iter().map(Option::is_some));
+        }
+        Ok(exist)
+    }
+    async fn get_balance(&self, pubkey: &Pubkey) -> Result<u64> {
+        let lamports = self.get_async_client()?.get_balance(pubkey).await?;
+        Ok(lamports)
+    }
+    async fn get_latest_blockhash(&self) -> Result<Hash> {
+        let blockhash = self.get_async_client()?.get_latest_blockhash().await?;
+        Ok(blockhash)
+    }
+    /// 75th percentile of recent priority fees, in micro-lamports per compute unit
+    async fn get_recent_priority_fee(&self, accounts: &[Pubkey]) -> Result<u64> {
+        let mut fees = self
+            .get_async_client()?
+            .get_recent_prioritization_fees(accounts)
+            .await?
+            .into_iter()
+            .map(|fee| fee.prioritization_fee)
+            .collect::<Vec<_>>();
+        fees.sort_unstable();
+        let fee = fees.get(fees.len() * 3 / 4).copied().unwrap_or_default();
+        Ok(fee)
+    }
+    async fn simulate_transaction(&self, tx: &VersionedTransaction) -> Result<Simulation> {
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            ..Default::default()
+        };
+        let result = self
+            .get_async_client()?
+            .simulate_transaction_with_config(tx, config)
+            .await?
+            .value;
+        let simulation = Simulation {
+            err: result.err,
+            logs: result.logs.unwrap_or_default(),
+            units_consumed: result.units_consumed,
+        };
+        Ok(simulation)
+    }
 }
 
+#[async_trait]
+impl AsyncClient for helius::Helius {
+    fn get_async_client(&self) -> Result<&RpcClient> {
+        let res = match &self.async_rpc_client {
+            Some(rpc) => Ok(rpc.as_ref()),
+            None => Err(MissingHeliusSolanaAsyncClient),
+        };
+        res.map_err(From::from)
+    }
+}
+
+#[async_trait]
+impl AsyncClient for RpcClient {
+    fn get_async_client(&self) -> Result<&RpcClient> {
+        Ok(self)
+    }
+}
+
+/// accounts per get multiple accounts request
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// byte offset of `Stake.boost` in account data, past the 8 byte discriminator
+pub const STAKE_BOOST_OFFSET: usize = 8 + std::mem::offset_of!(Stake, boost);
+
+/// byte offset of the authority in lookup table account data,
+/// past the u32 state tag, deactivation slot, last extended slot and start index,
+/// and the option tag
+const LOOKUP_TABLE_AUTHORITY_OFFSET: usize = 4 + 8 + 8 + 1 + 1;
+
+/// memcmp filter as (offset, bytes)
+pub type MemcmpFilter = (usize, Vec<u8>);
+
+/// fetch program accounts of type T matching the memcmp filters
+///
+/// filters are applied by the rpc, the account discriminator included.
+/// if the provider rejects the filters,
+/// falls back to fetching every account and filtering client side.
 async fn get_program_accounts<T>(
     client: &RpcClient,
     program_id: &Pubkey,
-    filters: Vec<RpcFilterType>,
+    filters: Vec<MemcmpFilter>,
 ) -> Result<Vec<(Pubkey, T)>>
 where
     T: AccountDeserialize + Discriminator + Copy,
 {
-    let mut all_filters = vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
-        0,
-        T::discriminator().to_le_bytes().to_vec(),
-    ))];
+    let mut all_filters = vec![(0, T::discriminator().to_le_bytes().to_vec())];
     all_filters.extend(filters);
-    let result = client
-        .get_program_accounts_with_config(
-            program_id,
-            RpcProgramAccountsConfig {
-                // filters: Some(all_filters),
-                filters: None,
-                account_config: RpcAccountInfoConfig {
-                    encoding: Some(UiAccountEncoding::Base64),
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-        )
-        .await?;
+    let rpc_filters = all_filters
+        .iter()
+        .map(|(offset, bytes)| RpcFilterType::Memcmp(Memcmp::new_raw_bytes(*offset, bytes.clone())))
+        .collect::<Vec<_>>();
+    let config = |filters: Option<Vec<RpcFilterType>>| RpcProgramAccountsConfig {
+        filters,
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let result = match client
+        .get_program_accounts_with_config(program_id, config(Some(rpc_filters)))
+        .await
+    {
+        Ok(result) => result,
+        Err(err) => {
+            log::warn!(
+                "filtered get program accounts failed, falling back to client side filters: {:?}",
+                err
+            );
+            client
+                .get_program_accounts_with_config(program_id, config(None))
+                .await?
+                .into_iter()
+                .filter(|(_, account)| memcmp_matches(all_filters.as_slice(), &account.data))
+                .collect()
+        }
+    };
     let accounts = result
         .into_iter()
         .flat_map(|(pubkey, account)| {
@@ -398,25 +898,165 @@ where
     Ok(accounts)
 }
 
-fn helius_api_key() -> Result<String> {
-    let key = std::env::var("HELIUS_API_KEY")?;
-    Ok(key)
+fn memcmp_matches(filters: &[MemcmpFilter], data: &[u8]) -> bool {
+    filters.iter().all(|(offset, bytes)| {
+        data.get(*offset..offset + bytes.len())
+            .is_some_and(|slice| slice.eq(bytes.as_slice()))
+    })
 }
 
-fn helius_cluster() -> Result<Cluster> {
-    let cluster_str = std::env::var("HELIUS_CLUSTER")?;
-    let res = match cluster_str.as_str() {
-        "mainnet" => Ok(Cluster::MainnetBeta),
-        "mainnet-staked" => Ok(Cluster::StakedMainnetBeta),
-        "devnet" => Ok(Cluster::Devnet),
+fn helius_cluster(cluster: Option<&str>) -> Result<Cluster> {
+    let res = match cluster {
+        Some("mainnet") => Ok(Cluster::MainnetBeta),
+        Some("mainnet-staked") => Ok(Cluster::StakedMainnetBeta),
+        Some("devnet") => Ok(Cluster::Devnet),
         _ => Err(InvalidHeliusCluster),
     };
     res.map_err(From::from)
 }
 
-fn keypair() -> Result<Keypair> {
-    let keypair_path = std::env::var("KEYPAIR_PATH")?;
+fn keypair(keypair_path: &str) -> Result<Keypair> {
     let keypair =
         Keypair::read_from_file(keypair_path).map_err(|err| anyhow::anyhow!(err.to_string()))?;
     Ok(keypair)
 }
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+
+    use super::*;
+    use crate::mock::MockChain;
+
+    #[test]
+    fn stake_boost_filter_matches_account_data() {
+        let boost = Pubkey::new_unique();
+        let mut stake = Stake::zeroed();
+        stake.boost = boost;
+        stake.id = 42;
+        let mut data = vec![0u8; 8];
+        data[0] = Stake::discriminator();
+        data.extend_from_slice(bytemuck::bytes_of(&stake));
+        let filters = [
+            (0, Stake::discriminator().to_le_bytes().to_vec()),
+            (STAKE_BOOST_OFFSET, boost.to_bytes().to_vec()),
+        ];
+        assert!(memcmp_matches(&filters, data.as_slice()));
+        assert_eq!(Stake::try_from_bytes(data.as_slice()).unwrap().boost, boost);
+        let other = [(STAKE_BOOST_OFFSET, Pubkey::new_unique().to_bytes().to_vec())];
+        assert!(!memcmp_matches(&other, data.as_slice()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn bundle_compiles_against_one_blockhash() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        for _ in 0..300 {
+            chain.add_stake(&boost);
+        }
+        let stakes = crate::stakes::StakeIndex::new(&client, &boost)
+            .await
+            .unwrap();
+        let (luts, stake_accounts) = crate::lookup_tables::sync(&client, &boost, &stakes)
+            .await
+            .unwrap();
+        let ixs = stake_accounts
+            .iter()
+            .take(3)
+            .map(|(stake, _)| ore_boost_api::sdk::rebase(client.keypair.pubkey(), mint, *stake))
+            .collect::<Vec<_>>();
+        let bundle = client
+            .build_jito_bundle_with_luts(
+                &boost,
+                &[
+                    (&ixs[..1], &luts[..1]),
+                    (&ixs[1..2], &luts[..]),
+                    (&ixs[2..], &[]),
+                ],
+            )
+            .await
+            .unwrap();
+        let blockhash = bundle.transactions[0].message.recent_blockhash();
+        assert!(bundle
+            .transactions
+            .iter()
+            .all(|tx| tx.message.recent_blockhash().eq(blockhash)));
+        // each transaction references only its own lookup tables
+        let lookups = bundle
+            .transactions
+            .iter()
+            .map(|tx| tx.message.address_table_lookups().unwrap_or_default().len())
+            .collect::<Vec<_>>();
+        assert!(lookups[0].le(&1) && lookups[2].eq(&0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dry_run_reports_without_sending() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        let stake = chain.add_stake(&boost);
+        let ix = ore_boost_api::sdk::rebase(client.keypair.pubkey(), mint, stake);
+        let bundle = client
+            .build_jito_bundle_with_luts(&boost, &[(&[ix.clone()], &[]), (&[ix], &[])])
+            .await
+            .unwrap();
+        let reports = client
+            .dry_run(bundle.transactions.as_slice(), bundle.tip.lamports)
+            .await
+            .unwrap();
+        assert_eq!(chain.submissions(), (0, 0));
+        assert_eq!(chain.checkpoint(&boost).current_id, 0);
+        assert_eq!(reports.len(), 2);
+        for (report, tx) in reports.iter().zip(bundle.transactions.iter()) {
+            assert_eq!(report.size, bincode::serialized_size(tx).unwrap() as usize);
+            assert_eq!(report.accounts, tx.message.static_account_keys().len());
+            assert_eq!(report.fee, LAMPORTS_PER_SIGNATURE);
+        }
+        // tip on the last
+        assert_eq!(reports[0].tip, 0);
+        assert_eq!(reports[1].tip, bundle.tip.lamports);
+        // priority fee from the compute budget
+        let budgeted = client
+            .compile_transaction(
+                &[
+                    ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+                    ComputeBudgetInstruction::set_compute_unit_price(1_000_000),
+                ],
+                &[],
+                Hash::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            expected_fee(&budgeted.message),
+            LAMPORTS_PER_SIGNATURE + 200_000
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dry_run_reports_every_failed_simulation() {
+        let chain = MockChain::new();
+        let client = chain.dry_run_client();
+        let (mint, boost) = chain.add_boost();
+        let stake = chain.add_stake(&boost);
+        let ix = ore_boost_api::sdk::rebase(client.keypair.pubkey(), mint, stake);
+        chain.fail_simulations();
+        // sizing falls back to a default budget rather than ending the pass
+        assert_eq!(
+            client.estimate_compute_units(&[ix.clone()]).await.unwrap(),
+            DEFAULT_UNITS_PER_IX
+        );
+        let sigs = client
+            .send_transactions_with_luts(&[(&[ix.clone()], &[]), (&[ix.clone()], &[])])
+            .await
+            .unwrap();
+        assert_eq!(sigs.len(), 2);
+        // and each transaction reports its own failure
+        let tx = client.create_transaction(&[ix]).await.unwrap();
+        let reports = client.dry_run(&[tx.clone(), tx], 0).await.unwrap();
+        assert_eq!(reports.len(), 2);
+        assert!(reports.iter().all(|report| report.err.is_some()));
+        assert_eq!(chain.submissions(), (0, 0));
+    }
+}
@@ -0,0 +1,579 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::Result;
+use serde::{Deserialize, Deserializer};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::Error::InvalidConfig;
+use crate::jito::{self, TipConfig};
+use crate::scheduler;
+use crate::submit::Strategy;
+
+/// command line flags, over the config file and environment
+#[derive(clap::Args, Debug, Default)]
+pub struct Flags {
+    /// toml config file
+    #[arg(long, env = "CONFIG_PATH", global = true)]
+    pub config: Option<String>,
+    #[arg(long, global = true)]
+    pub rpc_url: Option<String>,
+    #[arg(long, global = true)]
+    pub ws_url: Option<String>,
+    #[arg(long, global = true)]
+    pub keypair_path: Option<String>,
+    #[arg(long, global = true)]
+    pub luts_path: Option<String>,
+    /// comma separated mints to run, every live boost on chain if unset
+    #[arg(long, value_delimiter = ',', global = true)]
+    pub mints: Option<Vec<String>>,
+    /// default submission strategy: jito, rpc or helius
+    #[arg(long, global = true)]
+    pub strategy: Option<Strategy>,
+    /// default fallback strategy, or none
+    #[arg(long, global = true)]
+    pub fallback: Option<String>,
+    /// check landed signatures after each batch
+    #[arg(long, global = true)]
+    pub verify_signatures: bool,
+    /// build and simulate transactions, but never submit them
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+}
+
+/// worker configuration
+///
+/// layered from defaults, then the toml file,
+/// then the environment, then command line flags,
+/// and validated once at startup
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// plain rpc, used over helius if set
+    pub rpc_url: Option<String>,
+    pub helius_api_key: Option<String>,
+    /// mainnet, mainnet-staked or devnet
+    pub helius_cluster: Option<String>,
+    /// websocket for checkpoint and stake account subscriptions
+    pub ws_url: Option<String>,
+    pub keypair_path: String,
+    /// lookup table registry path, suffixed by each boost
+    pub luts_path: String,
+    /// mints to run, every live boost on chain if empty
+    pub mints: Vec<String>,
+    /// check landed signatures after each batch
+    pub verify_signatures: bool,
+    /// seconds to wait after a failed checkpoint cycle
+    pub retry_secs: u64,
+    /// build and simulate transactions, but never submit them
+    pub dry_run: bool,
+    pub jito: JitoConfig,
+    pub luts: LutsConfig,
+    /// defaults for every boost
+    pub boost: BoostConfig,
+    /// overrides by mint
+    pub boosts: HashMap<String, BoostOverrides>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct JitoConfig {
+    pub block_engines: Vec<String>,
+    pub tip: TipConfig,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct LutsConfig {
+    /// deactivate and close obsolete lookup tables
+    pub gc: bool,
+    /// repack fragmented lookup tables
+    pub compact: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoostConfig {
+    pub strategy: Strategy,
+    /// strategy after the primary fails repeatedly, or "none"
+    #[serde(deserialize_with = "none_or")]
+    pub fallback: Option<Strategy>,
+    /// max rebases per transaction, as many as fit if unset
+    pub batch_size: Option<usize>,
+    /// seconds past the checkpoint deadline before firing
+    pub margin_secs: u64,
+    /// max random seconds past the margin
+    pub jitter_secs: u64,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoostOverrides {
+    pub strategy: Option<Strategy>,
+    #[serde(deserialize_with = "some_none_or")]
+    pub fallback: Option<Option<Strategy>>,
+    pub batch_size: Option<usize>,
+    pub margin_secs: Option<u64>,
+    pub jitter_secs: Option<u64>,
+    pub tip_min: Option<u64>,
+    pub tip_max: Option<u64>,
+    pub tip_per_checkpoint: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            rpc_url: None,
+            helius_api_key: None,
+            helius_cluster: None,
+            ws_url: None,
+            keypair_path: String::new(),
+            luts_path: String::new(),
+            mints: vec![],
+            verify_signatures: false,
+            retry_secs: 10,
+            dry_run: false,
+            jito: JitoConfig::default(),
+            luts: LutsConfig::default(),
+            boost: BoostConfig::default(),
+            boosts: HashMap::new(),
+        }
+    }
+}
+
+impl Default for JitoConfig {
+    fn default() -> Self {
+        Self {
+            block_engines: jito::BLOCK_ENGINES
+                .iter()
+                .map(|url| url.to_string())
+                .collect(),
+            tip: TipConfig::default(),
+        }
+    }
+}
+
+impl Default for BoostConfig {
+    fn default() -> Self {
+        Self {
+            strategy: Strategy::Jito,
+            fallback: Some(Strategy::Rpc),
+            batch_size: None,
+            margin_secs: scheduler::MARGIN_SECS,
+            jitter_secs: scheduler::MAX_JITTER_SECS,
+        }
+    }
+}
+
+impl Config {
+    /// load and validate,
+    /// reading the toml file at --config or CONFIG_PATH if set
+    pub fn load(flags: &Flags) -> Result<Self> {
+        let mut config = match flags.config.as_deref() {
+            Some(path) => {
+                log::info!("reading config: {}", path);
+                Self::from_toml(std::fs::read_to_string(path)?.as_str())?
+            }
+            None => Self::default(),
+        };
+        config.apply_env(&std::env::vars().collect())?;
+        config.apply_flags(flags)?;
+        config.validate()?;
+        Ok(config)
+    }
+    fn from_toml(str: &str) -> Result<Self> {
+        toml::from_str(str).map_err(|err| anyhow::anyhow!(InvalidConfig(err.to_string())))
+    }
+    /// settings for the boost of the mint, with its overrides
+    pub fn boost(&self, mint: &Pubkey) -> BoostConfig {
+        let mut boost = self.boost.clone();
+        if let Some(overrides) = self.boosts.get(&mint.to_string()) {
+            if let Some(strategy) = overrides.strategy {
+                boost.strategy = strategy;
+            }
+            if let Some(fallback) = overrides.fallback {
+                boost.fallback = fallback;
+            }
+            if let Some(batch_size) = overrides.batch_size {
+                boost.batch_size = Some(batch_size);
+            }
+            if let Some(margin_secs) = overrides.margin_secs {
+                boost.margin_secs = margin_secs;
+            }
+            if let Some(jitter_secs) = overrides.jitter_secs {
+                boost.jitter_secs = jitter_secs;
+            }
+        }
+        boost.fallback = boost
+            .fallback
+            .filter(|fallback| fallback.ne(&boost.strategy));
+        boost
+    }
+    /// tip config for the boost of the mint, with its overrides
+    pub fn tip(&self, mint: &Pubkey) -> TipConfig {
+        let mut tip = self.jito.tip.clone();
+        if let Some(overrides) = self.boosts.get(&mint.to_string()) {
+            tip.min = overrides.tip_min.unwrap_or(tip.min);
+            tip.max = overrides.tip_max.unwrap_or(tip.max);
+            tip.per_checkpoint = overrides.tip_per_checkpoint.unwrap_or(tip.per_checkpoint);
+        }
+        tip
+    }
+    /// tip configs of boosts with tip overrides, keyed by boost address
+    pub fn boost_tips(&self) -> Result<HashMap<Pubkey, TipConfig>> {
+        let mut tips = HashMap::new();
+        for (mint, overrides) in self.boosts.iter() {
+            if overrides.tip_min.is_none()
+                && overrides.tip_max.is_none()
+                && overrides.tip_per_checkpoint.is_none()
+            {
+                continue;
+            }
+            let mint = Pubkey::from_str(mint)?;
+            let (boost, _) = ore_boost_api::state::boost_pda(mint);
+            tips.insert(boost, self.tip(&mint));
+        }
+        Ok(tips)
+    }
+    /// configured mints, or none to run every live boost on chain
+    pub fn mints(&self) -> Result<Option<Vec<Pubkey>>> {
+        if self.mints.is_empty() {
+            return Ok(None);
+        }
+        let mut mints = vec![];
+        for mint in self.mints.iter() {
+            mints.push(Pubkey::from_str(mint)?);
+        }
+        Ok(Some(mints))
+    }
+    /// override with the environment variables that are set
+    fn apply_env(&mut self, env: &HashMap<String, String>) -> Result<()> {
+        fn var<T: FromStr>(env: &HashMap<String, String>, key: &str) -> Result<Option<T>>
+        where
+            T::Err: Display,
+        {
+            env.get(key)
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|err| anyhow::anyhow!(InvalidConfig(format!("{}: {}", key, err))))
+                })
+                .transpose()
+        }
+        fn flag(env: &HashMap<String, String>, key: &str) -> Option<bool> {
+            env.get(key).map(|v| v.eq("true") || v.eq("1"))
+        }
+        fn list(value: &str) -> Vec<String> {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect()
+        }
+        for (key, field) in [
+            ("RPC_URL", &mut self.rpc_url),
+            ("HELIUS_API_KEY", &mut self.helius_api_key),
+            ("HELIUS_CLUSTER", &mut self.helius_cluster),
+            ("WS_URL", &mut self.ws_url),
+        ] {
+            if let Some(value) = env.get(key) {
+                *field = Some(value.clone());
+            }
+        }
+        if let Some(keypair_path) = env.get("KEYPAIR_PATH") {
+            self.keypair_path = keypair_path.clone();
+        }
+        if let Some(luts_path) = env.get("LUTS_PATH") {
+            self.luts_path = luts_path.clone();
+        }
+        if let Some(mints) = env.get("MINTS").or_else(|| env.get("MINT")) {
+            self.mints = list(mints);
+        }
+        if let Some(verify_signatures) = flag(env, "VERIFY_SIGNATURES") {
+            self.verify_signatures = verify_signatures;
+        }
+        if let Some(retry_secs) = var(env, "RETRY_SECS")? {
+            self.retry_secs = retry_secs;
+        }
+        if let Some(dry_run) = flag(env, "DRY_RUN") {
+            self.dry_run = dry_run;
+        }
+        if let Some(gc) = flag(env, "LUTS_GC") {
+            self.luts.gc = gc;
+        }
+        if let Some(compact) = flag(env, "LUTS_COMPACT") {
+            self.luts.compact = compact;
+        }
+        // jito
+        if let Some(block_engines) = env.get("JITO_BLOCK_ENGINES") {
+            self.jito.block_engines = list(block_engines);
+        }
+        let tip = &mut self.jito.tip;
+        tip.min = var(env, "JITO_TIP_MIN")?.unwrap_or(tip.min);
+        tip.max = var(env, "JITO_TIP_MAX")?.unwrap_or(tip.max);
+        tip.percentile = var(env, "JITO_TIP_PERCENTILE")?.unwrap_or(tip.percentile);
+        tip.per_checkpoint = var(env, "JITO_TIP_CHECKPOINT_CAP")?.unwrap_or(tip.per_checkpoint);
+        tip.per_day = var(env, "JITO_TIP_DAILY_CAP")?.unwrap_or(tip.per_day);
+        if let Some(floor_url) = env.get("JITO_TIP_FLOOR_URL") {
+            tip.floor_url = parse_none(floor_url)?;
+        }
+        // boost defaults
+        if let Some(strategy) = var(env, "SUBMIT_STRATEGY")? {
+            self.boost.strategy = strategy;
+        }
+        if let Some(fallback) = env.get("SUBMIT_FALLBACK") {
+            self.boost.fallback = parse_none(fallback)?;
+        }
+        if let Some(margin_secs) = var(env, "SCHEDULE_MARGIN_SECS")? {
+            self.boost.margin_secs = margin_secs;
+        }
+        if let Some(jitter_secs) = var(env, "SCHEDULE_JITTER_SECS")? {
+            self.boost.jitter_secs = jitter_secs;
+        }
+        // per boost strategies
+        for (key, value) in env.iter() {
+            if let Some(mint) = key.strip_prefix("SUBMIT_STRATEGY_") {
+                let strategy = Strategy::from_str(value)?;
+                self.boosts.entry(mint.to_string()).or_default().strategy = Some(strategy);
+            }
+        }
+        Ok(())
+    }
+    /// override with the command line flags that are set
+    fn apply_flags(&mut self, flags: &Flags) -> Result<()> {
+        for (flag, field) in [
+            (&flags.rpc_url, &mut self.rpc_url),
+            (&flags.ws_url, &mut self.ws_url),
+        ] {
+            if let Some(value) = flag {
+                *field = Some(value.clone());
+            }
+        }
+        if let Some(keypair_path) = flags.keypair_path.as_ref() {
+            self.keypair_path = keypair_path.clone();
+        }
+        if let Some(luts_path) = flags.luts_path.as_ref() {
+            self.luts_path = luts_path.clone();
+        }
+        if let Some(mints) = flags.mints.as_ref() {
+            self.mints = mints.clone();
+        }
+        if let Some(strategy) = flags.strategy {
+            self.boost.strategy = strategy;
+        }
+        if let Some(fallback) = flags.fallback.as_ref() {
+            self.boost.fallback = parse_none(fallback)?;
+        }
+        if flags.verify_signatures {
+            self.verify_signatures = true;
+        }
+        if flags.dry_run {
+            self.dry_run = true;
+        }
+        Ok(())
+    }
+    fn validate(&self) -> Result<()> {
+        let invalid =
+            |reason: String| -> Result<()> { Err(anyhow::anyhow!(InvalidConfig(reason))) };
+        if self.keypair_path.is_empty() {
+            return invalid("keypair_path is required".to_string());
+        }
+        if self.luts_path.is_empty() {
+            return invalid("luts_path is required".to_string());
+        }
+        if self.rpc_url.is_none() {
+            if self.helius_api_key.is_none() {
+                return invalid("rpc_url or helius_api_key is required".to_string());
+            }
+            match self.helius_cluster.as_deref() {
+                Some("mainnet") | Some("mainnet-staked") | Some("devnet") => {}
+                cluster => return invalid(format!("helius_cluster: {:?}", cluster)),
+            }
+        }
+        for mint in self.mints.iter().chain(self.boosts.keys()) {
+            if Pubkey::from_str(mint).is_err() {
+                return invalid(format!("mint: {}", mint));
+            }
+        }
+        if self.jito.block_engines.is_empty() {
+            return invalid("jito.block_engines is empty".to_string());
+        }
+        if ![25, 50, 75, 95, 99].contains(&self.jito.tip.percentile) {
+            return invalid(format!("jito.tip.percentile: {}", self.jito.tip.percentile));
+        }
+        for mint in std::iter::once(None).chain(self.boosts.keys().map(Some)) {
+            let (tip, boost) = match mint {
+                Some(mint) => {
+                    let mint = Pubkey::from_str(mint)?;
+                    (self.tip(&mint), self.boost(&mint))
+                }
+                None => (self.jito.tip.clone(), self.boost.clone()),
+            };
+            let section = mint.map_or("boost".to_string(), |mint| format!("boosts.{}", mint));
+            if tip.min.gt(&tip.max) {
+                return invalid(format!(
+                    "{}: tip min {} over max {}",
+                    section, tip.min, tip.max
+                ));
+            }
+            if boost.batch_size.is_some_and(|batch_size| batch_size.eq(&0)) {
+                return invalid(format!("{}: batch_size is zero", section));
+            }
+            let helius = [Some(boost.strategy), boost.fallback].contains(&Some(Strategy::Helius));
+            if helius && self.rpc_url.is_some() {
+                return invalid(format!(
+                    "{}: helius strategy requires the helius backend, unset rpc_url",
+                    section
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// "none" as none, otherwise parsed
+fn parse_none<T: FromStr>(str: &str) -> Result<Option<T>>
+where
+    T::Err: Display,
+{
+    if str.eq("none") {
+        return Ok(None);
+    }
+    str.parse()
+        .map(Some)
+        .map_err(|err| anyhow::anyhow!(InvalidConfig(format!("{}: {}", str, err))))
+}
+
+pub fn none_or<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let str = String::deserialize(deserializer)?;
+    parse_none(str.as_str()).map_err(serde::de::Error::custom)
+}
+
+fn some_none_or<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    none_or(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_layers_file_env_and_flags() {
+        let mint = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let toml = format!(
+            r#"
+            rpc_url = "http://file"
+            keypair_path = "id.json"
+            luts_path = "luts"
+
+            [jito.tip]
+            max = 1000000
+            floor_url = "none"
+
+            [boost]
+            strategy = "rpc"
+            batch_size = 8
+
+            [boosts.{mint}]
+            strategy = "jito"
+            fallback = "none"
+            tip_max = 2000000
+            "#
+        );
+        let mut config = Config::from_toml(toml.as_str()).unwrap();
+        config
+            .apply_env(&HashMap::from([
+                ("RPC_URL".to_string(), "http://env".to_string()),
+                ("LUTS_GC".to_string(), "1".to_string()),
+                ("SCHEDULE_MARGIN_SECS".to_string(), "7".to_string()),
+            ]))
+            .unwrap();
+        config
+            .apply_flags(&Flags {
+                rpc_url: Some("http://flag".to_string()),
+                mints: Some(vec![mint.to_string()]),
+                dry_run: true,
+                ..Default::default()
+            })
+            .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.rpc_url.as_deref(), Some("http://flag"));
+        assert!(config.luts.gc);
+        assert!(config.dry_run);
+        assert_eq!(config.mints().unwrap(), Some(vec![mint]));
+        assert!(config.jito.tip.floor_url.is_none());
+        // boost defaults and overrides
+        let boost = config.boost(&other);
+        assert_eq!(boost.strategy, Strategy::Rpc);
+        assert_eq!(boost.fallback, None);
+        assert_eq!(boost.batch_size, Some(8));
+        assert_eq!(boost.margin_secs, 7);
+        let boost = config.boost(&mint);
+        assert_eq!(boost.strategy, Strategy::Jito);
+        assert_eq!(boost.fallback, None);
+        assert_eq!(boost.batch_size, Some(8));
+        assert_eq!(config.tip(&other).max, 1_000_000);
+        assert_eq!(config.tip(&mint).max, 2_000_000);
+        let (boost_pda, _) = ore_boost_api::state::boost_pda(mint);
+        assert_eq!(
+            config.boost_tips().unwrap().keys().collect::<Vec<_>>(),
+            vec![&boost_pda]
+        );
+    }
+
+    #[test]
+    fn invalid_config_is_rejected() {
+        let valid = Config {
+            rpc_url: Some("http://localhost:8899".to_string()),
+            keypair_path: "id.json".to_string(),
+            luts_path: "luts".to_string(),
+            ..Default::default()
+        };
+        valid.validate().unwrap();
+        assert!(Config::from_toml("unknown = 1").is_err());
+        assert!(Config::from_toml("[boost]\nstrategy = \"carrier-pigeon\"").is_err());
+        let mut config = valid.clone();
+        config.keypair_path = String::new();
+        assert!(config.validate().is_err());
+        let mut config = valid.clone();
+        config.rpc_url = None;
+        config.helius_api_key = Some("key".to_string());
+        config.helius_cluster = Some("testnet".to_string());
+        assert!(config.validate().is_err());
+        let mut config = valid.clone();
+        config.boost.strategy = Strategy::Helius;
+        assert!(config.validate().is_err());
+        let mut config = valid.clone();
+        config.mints = vec!["not a pubkey".to_string()];
+        assert!(config.validate().is_err());
+        let mut config = valid.clone();
+        config.boosts.insert(
+            Pubkey::new_unique().to_string(),
+            BoostOverrides {
+                tip_min: Some(config.jito.tip.max + 1),
+                ..Default::default()
+            },
+        );
+        assert!(config.validate().is_err());
+        let mut config = valid;
+        assert!(config
+            .apply_env(&HashMap::from([(
+                "JITO_TIP_MIN".to_string(),
+                "lots".to_string()
+            )]))
+            .is_err());
+    }
+}
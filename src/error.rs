@@ -4,16 +4,44 @@ pub enum Error {
     InvalidHeliusCluster,
     #[error("missing async solana client")]
     MissingHeliusSolanaAsyncClient,
+    #[error("helius strategy requires the helius backend")]
+    MissingHeliusBackend,
     #[error("invalid pubkey bytes")]
     InvalidPubkeyBytes,
-    #[error("clock still ticking")]
-    ClockStillTicking,
+    #[error("unsupported lookup table registry version: {0}")]
+    UnsupportedRegistryVersion(u32),
+    #[error("instruction does not fit in a transaction")]
+    InstructionTooLarge,
+    #[error("lookup table not warmed up")]
+    UnwarmedLookupTable,
+    #[error("invalid submit strategy: {0}")]
+    InvalidSubmitStrategy(String),
+    #[error("unconfirmed transactions")]
+    UnconfirmedTransactions,
+    #[error("checkpoint at id {actual}, expected {expected}")]
+    UnexpectedCheckpointProgress { expected: u64, actual: u64 },
+    #[error("checkpoint incomplete at id {0}, past every indexed stake account")]
+    IncompleteCheckpoint(u64),
+    #[error("websocket subscription closed")]
+    SubscriptionClosed,
     #[error("unconfirmed jito bundle")]
     UnconfirmedJitoBundle,
+    #[error("invalid jito bundle")]
+    InvalidJitoBundle,
     #[error("too many transactions in jito bundle")]
     TooManyTransactionsInJitoBundle,
     #[error("empty jito bundle")]
     EmptyJitoBundle,
     #[error("empty jito bundle confirmation")]
     EmptyJitoBundleConfirmation,
+    #[error("no jito block engines configured")]
+    NoJitoBlockEngines,
+    #[error("jito block engine rate limited, retry after {0:?}s")]
+    JitoRateLimited(Option<u64>),
+    #[error("jito block engine unavailable: {0}")]
+    JitoUnavailable(String),
+    #[error("jito tip spending cap reached")]
+    JitoTipCapReached,
+    #[error("invalid config: {0}")]
+    InvalidConfig(String),
 }
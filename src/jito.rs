@@ -0,0 +1,928 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rand::seq::SliceRandom;
+use solana_sdk::{
+    instruction::Instruction, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Signature,
+    system_instruction, transaction::VersionedTransaction,
+};
+use tokio::time::{Duration, Instant};
+
+use crate::error::Error::{
+    EmptyJitoBundle, EmptyJitoBundleConfirmation, JitoRateLimited, JitoTipCapReached,
+    JitoUnavailable, NoJitoBlockEngines, TooManyTransactionsInJitoBundle,
+};
+
+/// block engines tried in order, unless configured
+pub const BLOCK_ENGINES: [&str; 6] = [
+    "https://mainnet.block-engine.jito.wtf",
+    "https://amsterdam.mainnet.block-engine.jito.wtf",
+    "https://frankfurt.mainnet.block-engine.jito.wtf",
+    "https://ny.mainnet.block-engine.jito.wtf",
+    "https://tokyo.mainnet.block-engine.jito.wtf",
+    "https://slc.mainnet.block-engine.jito.wtf",
+];
+
+const BUNDLES_PATH: &str = "/api/v1/bundles";
+const INFLIGHT_BUNDLE_STATUSES_PATH: &str = "/api/v1/getInflightBundleStatuses";
+const BUNDLE_STATUSES_PATH: &str = "/api/v1/getBundleStatuses";
+
+/// inflight status polls, two seconds apart,
+/// about as long as a blockhash is valid
+const MAX_INFLIGHT_POLLS: u32 = 45;
+
+/// bundle status polls, two seconds apart, after landing
+const MAX_BUNDLE_STATUS_POLLS: u32 = 5;
+
+/// cooldown after the first failure, doubled per consecutive failure
+const RATE_LIMITED_COOLDOWN_SECS: u64 = 1;
+const UNAVAILABLE_COOLDOWN_SECS: u64 = 5;
+const MAX_COOLDOWN_SECS: u64 = 300;
+
+/// max transactions per bundle
+pub const MAX_TRANSACTIONS_PER_BUNDLE: usize = 5;
+
+/// lamports tipped on the last transaction of each bundle,
+/// before any landing history
+pub const TIP_LAMPORTS: u64 = 100_000;
+
+const TIP_FLOOR_URL: &str = "https://bundles.jito.wtf/api/v1/bundles/tip_floor";
+
+/// tip floor is refetched once stale
+const TIP_FLOOR_TTL_SECS: u64 = 60;
+
+/// default tip bounds and spending caps, in lamports
+const MIN_TIP_LAMPORTS: u64 = 10_000;
+const MAX_TIP_LAMPORTS: u64 = 5_000_000;
+const CHECKPOINT_TIP_CAP_LAMPORTS: u64 = 50_000_000;
+const DAILY_TIP_CAP_LAMPORTS: u64 = 1_000_000_000;
+
+const DAY_SECS: u64 = 86_400;
+
+const TIP_ACCOUNTS: [&str; 8] = [
+    "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+    "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+    "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
+    "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+    "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+    "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+    "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+    "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+];
+
+#[derive(serde::Serialize, Debug)]
+struct BasicRequest<T> {
+    jsonrpc: String,
+    id: u32,
+    method: String,
+    params: T,
+}
+
+impl<T> BasicRequest<T> {
+    fn new(method: &str, params: T) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: 1,
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+/// transfer tip to a random jito tip account
+pub fn tip_instruction(payer: &Pubkey, lamports: u64) -> Result<Instruction> {
+    let tip_account = TIP_ACCOUNTS
+        .choose(&mut rand::thread_rng())
+        .unwrap_or(&TIP_ACCOUNTS[0]);
+    let tip_account = Pubkey::from_str(tip_account)?;
+    let ix = system_instruction::transfer(payer, &tip_account, lamports);
+    Ok(ix)
+}
+
+/// tip bounds and spending caps, in lamports
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TipConfig {
+    pub min: u64,
+    pub max: u64,
+    /// tip floor percentile to tip at least
+    pub percentile: u8,
+    /// tips spent per boost per checkpoint
+    pub per_checkpoint: u64,
+    /// tips spent across boosts per day
+    pub per_day: u64,
+    /// tip floor api, none to tip from landing history alone
+    #[serde(deserialize_with = "crate::config::none_or")]
+    pub floor_url: Option<String>,
+}
+
+impl Default for TipConfig {
+    fn default() -> Self {
+        Self {
+            min: MIN_TIP_LAMPORTS,
+            max: MAX_TIP_LAMPORTS,
+            percentile: 50,
+            per_checkpoint: CHECKPOINT_TIP_CAP_LAMPORTS,
+            per_day: DAILY_TIP_CAP_LAMPORTS,
+            floor_url: Some(TIP_FLOOR_URL.to_string()),
+        }
+    }
+}
+
+/// adaptive jito tip
+///
+/// tips at least the tip floor percentile,
+/// raises the tip after unlanded bundles and decays it after landed ones.
+/// only landed bundles pay.
+/// tips are reserved against the spending caps when taken,
+/// so bundles in flight at once cannot overshoot them together,
+/// and released if their bundle does not land.
+pub struct Tipper {
+    http: reqwest::Client,
+    config: TipConfig,
+    /// bounds and checkpoint caps overridden per boost
+    boosts: HashMap<Pubkey, TipConfig>,
+    state: Mutex<TipState>,
+}
+
+struct TipState {
+    /// adapted tip, keyed by boost address
+    lamports: HashMap<Pubkey, u64>,
+    /// last tip floor and when it was fetched
+    floor: Option<(Instant, u64)>,
+    day_start: Instant,
+    /// paid and reserved
+    spent_today: u64,
+    /// paid and reserved, keyed by boost address
+    spent_checkpoint: HashMap<Pubkey, u64>,
+    /// checkpoint resets, keyed by boost address
+    checkpoints: HashMap<Pubkey, u64>,
+}
+
+/// tip reserved against the spending caps
+///
+/// kept as spent once reported landed,
+/// released once reported otherwise or dropped unreported
+pub struct Reservation {
+    tipper: Arc<Tipper>,
+    boost: Pubkey,
+    pub lamports: u64,
+    /// day reserved against
+    day_start: Instant,
+    /// checkpoint of the boost reserved against
+    checkpoint: u64,
+    reported: bool,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if !self.reported {
+            self.tipper.release(self);
+        }
+    }
+}
+
+impl Tipper {
+    pub fn new(config: TipConfig, boosts: HashMap<Pubkey, TipConfig>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            config,
+            boosts,
+            state: Mutex::new(TipState {
+                lamports: HashMap::new(),
+                floor: None,
+                day_start: Instant::now(),
+                spent_today: 0,
+                spent_checkpoint: HashMap::new(),
+                checkpoints: HashMap::new(),
+            }),
+        }
+    }
+    /// reserve the tip for the next bundle of the boost,
+    /// errors if it would exceed a spending cap
+    pub async fn tip(self: &Arc<Self>, boost: &Pubkey) -> Result<Reservation> {
+        let floor = self.floor().await;
+        let mut state = self.state.lock().unwrap();
+        if state.day_start.elapsed().as_secs().ge(&DAY_SECS) {
+            state.day_start = Instant::now();
+            state.spent_today = 0;
+        }
+        let config = self.config(boost);
+        let lamports = state
+            .lamports
+            .get(boost)
+            .copied()
+            .unwrap_or(TIP_LAMPORTS)
+            .max(floor.unwrap_or_default())
+            .clamp(config.min, config.max.max(config.min));
+        let spent_checkpoint = state
+            .spent_checkpoint
+            .get(boost)
+            .copied()
+            .unwrap_or_default();
+        if (spent_checkpoint + lamports).gt(&config.per_checkpoint)
+            || (state.spent_today + lamports).gt(&self.config.per_day)
+        {
+            return Err(anyhow::anyhow!(JitoTipCapReached));
+        }
+        state.spent_today += lamports;
+        *state.spent_checkpoint.entry(*boost).or_default() += lamports;
+        Ok(Reservation {
+            tipper: self.clone(),
+            boost: *boost,
+            lamports,
+            day_start: state.day_start,
+            checkpoint: state.checkpoints.get(boost).copied().unwrap_or_default(),
+            reported: false,
+        })
+    }
+    /// record a bundle outcome,
+    /// paying the tip if landed and raising it if not
+    pub fn report(&self, mut tip: Reservation, outcome: &Outcome) {
+        tip.reported = true;
+        let (boost, lamports) = (&tip.boost, tip.lamports);
+        let config = self.config(boost);
+        if !matches!(outcome, Outcome::Landed { .. }) {
+            self.release(&tip);
+        }
+        let mut state = self.state.lock().unwrap();
+        match outcome {
+            Outcome::Landed { .. } => {
+                // decay towards the floor
+                state
+                    .lamports
+                    .insert(*boost, (lamports - lamports / 10).max(config.min));
+            }
+            Outcome::Failed | Outcome::Expired => {
+                let raised = (lamports + lamports / 2).min(config.max);
+                state.lamports.insert(*boost, raised);
+                log::info!(
+                    "{:?} -- bundle did not land, raising tip to {} SOL",
+                    boost,
+                    raised as f64 / LAMPORTS_PER_SOL as f64
+                );
+            }
+            // rejected, not outbid
+            Outcome::Invalid => {}
+        }
+    }
+    /// tip bounds and checkpoint cap of the boost,
+    /// the daily cap and tip floor are shared
+    fn config(&self, boost: &Pubkey) -> &TipConfig {
+        self.boosts.get(boost).unwrap_or(&self.config)
+    }
+    /// return a reserved tip that was never paid,
+    /// unless its day or checkpoint has since been reset
+    fn release(&self, tip: &Reservation) {
+        let mut state = self.state.lock().unwrap();
+        if state.day_start.eq(&tip.day_start) {
+            state.spent_today = state.spent_today.saturating_sub(tip.lamports);
+        }
+        let checkpoint = state
+            .checkpoints
+            .get(&tip.boost)
+            .copied()
+            .unwrap_or_default();
+        if checkpoint.eq(&tip.checkpoint) {
+            if let Some(spent) = state.spent_checkpoint.get_mut(&tip.boost) {
+                *spent = spent.saturating_sub(tip.lamports);
+            }
+        }
+    }
+    /// new checkpoint for the boost, resetting its spending cap
+    pub fn reset_checkpoint(&self, boost: &Pubkey) {
+        let mut state = self.state.lock().unwrap();
+        state.spent_checkpoint.remove(boost);
+        *state.checkpoints.entry(*boost).or_default() += 1;
+    }
+    /// tip floor percentile in lamports, refetched once stale.
+    /// none if unconfigured or unavailable
+    async fn floor(&self) -> Option<u64> {
+        let url = self.config.floor_url.as_ref()?;
+        let cached = self.state.lock().unwrap().floor;
+        if let Some((fetched, lamports)) = cached {
+            if fetched.elapsed().as_secs().lt(&TIP_FLOOR_TTL_SECS) {
+                return Some(lamports);
+            }
+        }
+        match self.fetch_floor(url).await {
+            Ok(lamports) => {
+                self.state.lock().unwrap().floor = Some((Instant::now(), lamports));
+                Some(lamports)
+            }
+            Err(err) => {
+                log::warn!("tip floor unavailable: {:?}", err);
+                cached.map(|(_, lamports)| lamports)
+            }
+        }
+    }
+    async fn fetch_floor(&self, url: &str) -> Result<u64> {
+        let response = self
+            .http
+            .get(url::Url::parse(url)?)
+            .send()
+            .await?
+            .json::<serde_json::Value>()
+            .await?;
+        let key = format!("landed_tips_{}th_percentile", self.config.percentile);
+        let sol = response
+            .get(0)
+            .and_then(|floor| floor.get(key.as_str()))
+            .and_then(|sol| sol.as_f64())
+            .ok_or(anyhow::anyhow!(
+                "unexpected tip floor response: {}",
+                response
+            ))?;
+        Ok((sol * LAMPORTS_PER_SOL as f64) as u64)
+    }
+}
+
+/// final state of a sent bundle
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// landed at slot, with its transaction signatures if known
+    Landed {
+        slot: Option<u64>,
+        signatures: Vec<Signature>,
+    },
+    /// processed but failed to land, rebuild and resend
+    Failed,
+    /// rejected or unknown to the block engine, resending as is will not help
+    Invalid,
+    /// still pending once its blockhash expired, rebuild and resend
+    Expired,
+}
+
+/// bundle id and outcome
+#[derive(Debug, Clone)]
+pub struct Tracked {
+    pub bundle_id: String,
+    pub outcome: Outcome,
+}
+
+/// signed bundle, tipped in its last transaction
+pub struct Bundle {
+    pub transactions: Vec<VersionedTransaction>,
+    pub tip: Reservation,
+    /// when its blockhash was fetched
+    pub built: Instant,
+}
+
+/// inflight bundle status
+enum Inflight {
+    Pending,
+    Landed(Option<u64>),
+    Failed,
+    Invalid,
+}
+
+/// block engine endpoint and its health
+#[derive(Debug)]
+struct Endpoint {
+    url: String,
+    /// consecutive failures
+    failures: u32,
+    /// skipped until
+    cooldown: Option<Instant>,
+}
+
+/// jito block engines with failover
+///
+/// an endpoint that is rate limited or unavailable
+/// cools down with exponential backoff while the next one is used
+pub struct BlockEngines {
+    http: reqwest::Client,
+    endpoints: Mutex<Vec<Endpoint>>,
+}
+
+impl BlockEngines {
+    pub fn new(urls: Vec<String>) -> Result<Self> {
+        if urls.is_empty() {
+            return Err(anyhow::anyhow!(NoJitoBlockEngines));
+        }
+        let endpoints = urls
+            .into_iter()
+            .map(|url| Endpoint {
+                url: url.trim_end_matches('/').to_string(),
+                failures: 0,
+                cooldown: None,
+            })
+            .collect();
+        Ok(Self {
+            http: reqwest::Client::new(),
+            endpoints: Mutex::new(endpoints),
+        })
+    }
+    /// sends the bundle and tracks it until it lands, fails or expires
+    pub async fn send_bundle(&self, transactions: &[VersionedTransaction]) -> Result<Tracked> {
+        if transactions.len().gt(&MAX_TRANSACTIONS_PER_BUNDLE) {
+            return Err(anyhow::anyhow!(TooManyTransactionsInJitoBundle));
+        }
+        if transactions.is_empty() {
+            return Err(anyhow::anyhow!(EmptyJitoBundle));
+        }
+        let mut encoded = vec![];
+        for tx in transactions {
+            let bytes = bincode::serialize(tx)?;
+            encoded.push(solana_sdk::bs58::encode(bytes).into_string());
+        }
+        let request = BasicRequest::new("sendBundle", vec![encoded]);
+        let (url, response) = self.post(BUNDLES_PATH, &request, None).await?;
+        let bundle_id = response
+            .get("result")
+            .and_then(|result| result.as_str())
+            .ok_or(anyhow::anyhow!(
+                "unexpected send bundle response: {}",
+                response
+            ))?
+            .to_string();
+        log::info!("bundle id: {:?} via {}", bundle_id, url);
+        let signatures = transactions
+            .iter()
+            .map(|tx| tx.signatures[0])
+            .collect::<Vec<_>>();
+        let outcome = track(self, url.as_str(), bundle_id.as_str(), signatures).await?;
+        log::info!("bundle {:?} outcome: {:?}", bundle_id, outcome);
+        Ok(Tracked { bundle_id, outcome })
+    }
+    /// healthy endpoints in configured order,
+    /// then those cooling down, soonest ready first
+    fn candidates(&self, preferred: Option<&str>) -> Vec<String> {
+        let now = Instant::now();
+        let endpoints = self.endpoints.lock().unwrap();
+        let (mut healthy, mut cooling): (Vec<_>, Vec<_>) = endpoints
+            .iter()
+            .partition(|endpoint| endpoint.cooldown.is_none_or(|until| until.le(&now)));
+        // keep the preferred endpoint first while healthy
+        if let Some(preferred) = preferred {
+            healthy.sort_by_key(|endpoint| endpoint.url.ne(preferred));
+        }
+        cooling.sort_by_key(|endpoint| endpoint.cooldown);
+        healthy
+            .into_iter()
+            .chain(cooling)
+            .map(|endpoint| endpoint.url.clone())
+            .collect()
+    }
+    /// track endpoint health from a response
+    fn report(&self, url: &str, result: &Result<serde_json::Value>) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let Some(endpoint) = endpoints.iter_mut().find(|endpoint| endpoint.url.eq(url)) else {
+            return;
+        };
+        let err = match result {
+            Ok(_) => {
+                endpoint.failures = 0;
+                endpoint.cooldown = None;
+                return;
+            }
+            Err(err) => err,
+        };
+        let base = match err.downcast_ref() {
+            Some(JitoRateLimited(Some(secs))) => *secs,
+            Some(JitoRateLimited(None)) => RATE_LIMITED_COOLDOWN_SECS,
+            Some(JitoUnavailable(_)) => UNAVAILABLE_COOLDOWN_SECS,
+            // rejected by the block engine, not an endpoint failure
+            _ => return,
+        };
+        let secs = base
+            .saturating_mul(1 << endpoint.failures.min(16))
+            .min(MAX_COOLDOWN_SECS);
+        endpoint.failures += 1;
+        endpoint.cooldown = Some(Instant::now() + Duration::from_secs(secs));
+        log::warn!(
+            "block engine {} cooling down for {}s: {}",
+            endpoint.url,
+            secs,
+            err
+        );
+    }
+    /// post to the preferred endpoint, or the first healthy one,
+    /// failing over on rate limits and outages.
+    /// returns the endpoint that answered.
+    async fn post<T: serde::Serialize>(
+        &self,
+        path: &str,
+        request: &BasicRequest<T>,
+        preferred: Option<&str>,
+    ) -> Result<(String, serde_json::Value)> {
+        let mut last = anyhow::anyhow!(NoJitoBlockEngines);
+        for url in self.candidates(preferred) {
+            let result = post(&self.http, format!("{}{}", url, path).as_str(), request).await;
+            self.report(url.as_str(), &result);
+            match result {
+                Ok(response) => return Ok((url, response)),
+                Err(err) => match err.downcast_ref() {
+                    Some(JitoRateLimited(_)) | Some(JitoUnavailable(_)) => last = err,
+                    _ => return Err(err),
+                },
+            }
+        }
+        Err(last)
+    }
+}
+
+/// bundle status lookups, polled while tracking a sent bundle
+#[async_trait]
+trait BundleStatuses {
+    /// inflight status from the preferred endpoint, or another if it is down,
+    /// with the endpoint that answered
+    async fn inflight_status(&self, url: &str, bundle_id: &str) -> Result<(String, Inflight)>;
+    /// landed outcome from bundle statuses, none if not landed
+    async fn bundle_status(&self, url: &str, bundle_id: &str) -> Result<Option<Outcome>>;
+}
+
+#[async_trait]
+impl BundleStatuses for BlockEngines {
+    async fn inflight_status(&self, url: &str, bundle_id: &str) -> Result<(String, Inflight)> {
+        let request = BasicRequest::new(
+            "getInflightBundleStatuses",
+            vec![vec![bundle_id.to_string()]],
+        );
+        let (answered, response) = self
+            .post(INFLIGHT_BUNDLE_STATUSES_PATH, &request, Some(url))
+            .await?;
+        #[derive(serde::Deserialize, Debug)]
+        struct Inner {
+            status: String,
+            landed_slot: Option<u64>,
+        }
+        #[derive(serde::Deserialize, Debug)]
+        struct Middle {
+            value: Vec<Inner>,
+        }
+        #[derive(serde::Deserialize, Debug)]
+        struct Outer {
+            result: Middle,
+        }
+        let response: Outer = serde_json::from_value(response)?;
+        let first = response
+            .result
+            .value
+            .first()
+            .ok_or(anyhow::anyhow!(EmptyJitoBundleConfirmation))?;
+        log::info!("bundle status: {} via {}", first.status, answered);
+        let status = match first.status.as_str() {
+            "Landed" => Inflight::Landed(first.landed_slot),
+            "Failed" => Inflight::Failed,
+            "Invalid" => Inflight::Invalid,
+            _ => Inflight::Pending,
+        };
+        Ok((answered, status))
+    }
+    async fn bundle_status(&self, url: &str, bundle_id: &str) -> Result<Option<Outcome>> {
+        let request = BasicRequest::new("getBundleStatuses", vec![vec![bundle_id.to_string()]]);
+        let (_, response) = self.post(BUNDLE_STATUSES_PATH, &request, Some(url)).await?;
+        #[derive(serde::Deserialize, Debug)]
+        struct Inner {
+            slot: u64,
+            transactions: Vec<String>,
+        }
+        #[derive(serde::Deserialize, Debug)]
+        struct Middle {
+            value: Vec<Option<Inner>>,
+        }
+        #[derive(serde::Deserialize, Debug)]
+        struct Outer {
+            result: Middle,
+        }
+        let response: Outer = serde_json::from_value(response)?;
+        let Some(Some(first)) = response.result.value.into_iter().next() else {
+            return Ok(None);
+        };
+        let mut signatures = vec![];
+        for sig in first.transactions.iter() {
+            signatures.push(Signature::from_str(sig.as_str())?);
+        }
+        Ok(Some(Outcome::Landed {
+            slot: Some(first.slot),
+            signatures,
+        }))
+    }
+}
+
+/// poll inflight statuses while the bundle's blockhash is valid,
+/// falling back to bundle statuses once out of the inflight window.
+///
+/// only the endpoint that accepted the bundle knows it,
+/// so invalid from any other region is still pending.
+async fn track(
+    statuses: &impl BundleStatuses,
+    url: &str,
+    bundle_id: &str,
+    signatures: Vec<Signature>,
+) -> Result<Outcome> {
+    for _ in 0..MAX_INFLIGHT_POLLS {
+        match statuses.inflight_status(url, bundle_id).await {
+            Ok((_, Inflight::Landed(slot))) => {
+                return landed(statuses, url, bundle_id, slot, signatures).await;
+            }
+            Ok((_, Inflight::Failed)) => return Ok(Outcome::Failed),
+            // unknown to the engine, unless it already landed
+            Ok((answered, Inflight::Invalid)) if answered.eq(url) => {
+                let landed = statuses.bundle_status(url, bundle_id).await?;
+                return Ok(landed.unwrap_or(Outcome::Invalid));
+            }
+            Ok((answered, Inflight::Invalid)) => {
+                log::info!(
+                    "bundle {:?} unknown to {}, still pending",
+                    bundle_id,
+                    answered
+                );
+            }
+            Ok((_, Inflight::Pending)) => {}
+            Err(err) => log::error!("{:?}", err),
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+    // pending past the blockhash lifetime
+    let landed = statuses.bundle_status(url, bundle_id).await?;
+    Ok(landed.unwrap_or(Outcome::Expired))
+}
+
+/// landed outcome with signatures,
+/// allowing bundle statuses to catch up with the inflight status.
+/// falls back to the signatures sent if they never do.
+async fn landed(
+    statuses: &impl BundleStatuses,
+    url: &str,
+    bundle_id: &str,
+    slot: Option<u64>,
+    signatures: Vec<Signature>,
+) -> Result<Outcome> {
+    for _ in 0..MAX_BUNDLE_STATUS_POLLS {
+        if let Some(landed) = statuses.bundle_status(url, bundle_id).await? {
+            return Ok(landed);
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+    Ok(Outcome::Landed { slot, signatures })
+}
+
+async fn post<T: serde::Serialize>(
+    http: &reqwest::Client,
+    url: &str,
+    request: &BasicRequest<T>,
+) -> Result<serde_json::Value> {
+    let parsed_url = url::Url::parse(url)?;
+    let response = http
+        .post(parsed_url)
+        .json(request)
+        .send()
+        .await
+        .map_err(|err| anyhow::anyhow!(JitoUnavailable(err.to_string())))?;
+    let status = response.status();
+    if status.eq(&reqwest::StatusCode::TOO_MANY_REQUESTS) {
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        return Err(anyhow::anyhow!(JitoRateLimited(retry_after)));
+    }
+    if status.is_server_error() {
+        return Err(anyhow::anyhow!(JitoUnavailable(status.to_string())));
+    }
+    let response = response.json::<serde_json::Value>().await?;
+    if let Some(error) = response.get("error") {
+        return Err(anyhow::anyhow!(error.to_string()));
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn landed() -> Outcome {
+        Outcome::Landed {
+            slot: Some(0),
+            signatures: vec![],
+        }
+    }
+
+    /// scripted inflight statuses, pending once exhausted
+    struct Scripted {
+        inflight: Mutex<Vec<(&'static str, Inflight)>>,
+        landed: Option<Outcome>,
+    }
+
+    #[async_trait]
+    impl BundleStatuses for Scripted {
+        async fn inflight_status(&self, url: &str, _: &str) -> Result<(String, Inflight)> {
+            let mut inflight = self.inflight.lock().unwrap();
+            if inflight.is_empty() {
+                return Ok((url.to_string(), Inflight::Pending));
+            }
+            let (answered, status) = inflight.remove(0);
+            Ok((answered.to_string(), status))
+        }
+        async fn bundle_status(&self, _: &str, _: &str) -> Result<Option<Outcome>> {
+            Ok(self.landed.clone())
+        }
+    }
+
+    async fn tracked(inflight: Vec<(&'static str, Inflight)>, landed: Option<Outcome>) -> Outcome {
+        let statuses = Scripted {
+            inflight: Mutex::new(inflight),
+            landed,
+        };
+        track(&statuses, "https://a", "id", vec![Signature::default()])
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn track_classifies_outcomes() {
+        let status = Outcome::Landed {
+            slot: Some(1),
+            signatures: vec![Signature::new_unique()],
+        };
+        // landed with signatures from bundle statuses
+        let outcome = tracked(
+            vec![("https://a", Inflight::Landed(Some(1)))],
+            Some(status.clone()),
+        )
+        .await;
+        assert_eq!(outcome, status);
+        // or the signatures sent if they never catch up
+        let outcome = tracked(vec![("https://a", Inflight::Landed(Some(1)))], None).await;
+        assert_eq!(
+            outcome,
+            Outcome::Landed {
+                slot: Some(1),
+                signatures: vec![Signature::default()],
+            }
+        );
+        let outcome = tracked(vec![("https://a", Inflight::Failed)], None).await;
+        assert_eq!(outcome, Outcome::Failed);
+        // invalid from the accepting endpoint, unless already landed
+        let outcome = tracked(vec![("https://a", Inflight::Invalid)], None).await;
+        assert_eq!(outcome, Outcome::Invalid);
+        let outcome = tracked(vec![("https://a", Inflight::Invalid)], Some(status.clone())).await;
+        assert_eq!(outcome, status);
+        // invalid from another region is still pending
+        let outcome = tracked(
+            vec![
+                ("https://b", Inflight::Invalid),
+                ("https://a", Inflight::Failed),
+            ],
+            None,
+        )
+        .await;
+        assert_eq!(outcome, Outcome::Failed);
+        // pending past the blockhash lifetime
+        let outcome = tracked(vec![("https://b", Inflight::Invalid)], None).await;
+        assert_eq!(outcome, Outcome::Expired);
+    }
+
+    fn unavailable() -> Result<serde_json::Value> {
+        Err(anyhow::anyhow!(JitoUnavailable("503".to_string())))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tip_adapts_within_caps() {
+        let boost = Pubkey::new_unique();
+        let capped = Pubkey::new_unique();
+        let config = TipConfig {
+            min: 10_000,
+            max: 200_000,
+            per_checkpoint: 500_000,
+            per_day: 800_000,
+            floor_url: None,
+            ..Default::default()
+        };
+        let tipper = Arc::new(Tipper::new(
+            config.clone(),
+            HashMap::from([(
+                capped,
+                TipConfig {
+                    max: 20_000,
+                    ..config
+                },
+            )]),
+        ));
+        // bounded per boost
+        let tip = tipper.tip(&capped).await.unwrap();
+        assert_eq!(tip.lamports, 20_000);
+        // and adapted per boost
+        tipper.report(tip, &landed());
+        assert_eq!(tipper.tip(&capped).await.unwrap().lamports, 18_000);
+        let tip = tipper.tip(&boost).await.unwrap();
+        assert_eq!(tip.lamports, TIP_LAMPORTS);
+        // raised after an unlanded bundle, up to the max
+        tipper.report(tip, &Outcome::Expired);
+        let tip = tipper.tip(&boost).await.unwrap();
+        assert_eq!(tip.lamports, 150_000);
+        tipper.report(tip, &Outcome::Failed);
+        let tip = tipper.tip(&boost).await.unwrap();
+        assert_eq!(tip.lamports, 200_000);
+        // rejected bundles leave it
+        tipper.report(tip, &Outcome::Invalid);
+        let tip = tipper.tip(&boost).await.unwrap();
+        assert_eq!(tip.lamports, 200_000);
+        // decays after landing
+        tipper.report(tip, &landed());
+        let held = tipper.tip(&boost).await.unwrap();
+        assert_eq!(held.lamports, 180_000);
+        // in flight tips count against the checkpoint cap, 560_000 reserved
+        assert!(tipper.tip(&boost).await.is_err());
+        // and are released if never reported
+        drop(held);
+        let tip = tipper.tip(&boost).await.unwrap();
+        tipper.report(tip, &landed());
+        // checkpoint cap, 542_000 spent
+        assert!(tipper.tip(&boost).await.is_err());
+        tipper.reset_checkpoint(&boost);
+        for lamports in [162_000, 145_800] {
+            let tip = tipper.tip(&boost).await.unwrap();
+            assert_eq!(tip.lamports, lamports);
+            tipper.report(tip, &landed());
+        }
+        // daily cap, 707_800 spent
+        assert!(tipper.tip(&boost).await.is_err());
+        tipper.reset_checkpoint(&boost);
+        assert!(tipper.tip(&boost).await.is_err());
+        tokio::time::sleep(Duration::from_secs(DAY_SECS)).await;
+        assert!(tipper.tip(&boost).await.is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tips_release_within_their_period() {
+        let boosts = (0..4).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+        let tipper = Arc::new(Tipper::new(
+            TipConfig {
+                min: 100_000,
+                max: 100_000,
+                per_checkpoint: 200_000,
+                per_day: 500_000,
+                floor_url: None,
+                ..Default::default()
+            },
+            HashMap::new(),
+        ));
+        let spend = |boost: Pubkey| {
+            let tipper = tipper.clone();
+            async move {
+                let tip = tipper.tip(&boost).await.unwrap();
+                tipper.report(tip, &landed());
+            }
+        };
+        // held across a new checkpoint
+        let held = tipper.tip(&boosts[0]).await.unwrap();
+        tipper.reset_checkpoint(&boosts[0]);
+        spend(boosts[0]).await;
+        spend(boosts[0]).await;
+        // released from the day, not the new checkpoint
+        drop(held);
+        assert!(tipper.tip(&boosts[0]).await.is_err());
+        // held across a new day
+        tokio::time::sleep(Duration::from_secs(DAY_SECS)).await;
+        let held = tipper.tip(&boosts[1]).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(DAY_SECS)).await;
+        for boost in [boosts[1], boosts[2], boosts[2], boosts[3], boosts[3]] {
+            spend(boost).await;
+        }
+        // released from the checkpoint, not the new day
+        drop(held);
+        assert!(tipper.tip(&boosts[1]).await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failing_endpoints_cool_down() {
+        let urls = ["https://a", "https://b", "https://c"];
+        let engines = BlockEngines::new(urls.iter().map(|url| url.to_string()).collect()).unwrap();
+        assert_eq!(engines.candidates(None), urls);
+        // rate limited with retry-after, and an outage
+        engines.report(
+            "https://a",
+            &Err(anyhow::anyhow!(JitoRateLimited(Some(10)))),
+        );
+        engines.report("https://b", &unavailable());
+        assert_eq!(
+            engines.candidates(None),
+            ["https://c", "https://b", "https://a"]
+        );
+        // rejected bundles do not count against the endpoint
+        engines.report("https://c", &Err(anyhow::anyhow!("bundle rejected")));
+        assert_eq!(engines.candidates(None)[0], "https://c");
+        // preferred endpoint first, only while healthy
+        assert_eq!(engines.candidates(Some("https://a"))[0], "https://c");
+        // healthy again after cooldown
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(engines.candidates(None), urls);
+        assert_eq!(engines.candidates(Some("https://c"))[0], "https://c");
+        // backoff doubles on consecutive failures
+        engines.report("https://b", &unavailable());
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(engines.candidates(None)[2], "https://b");
+        // recovers on success
+        engines.report("https://b", &Ok(serde_json::Value::Null));
+        assert_eq!(engines.candidates(None), urls);
+    }
+}
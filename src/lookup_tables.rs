@@ -1,34 +1,106 @@
-use std::{
-    fs::{File, OpenOptions},
-    io::{BufRead, BufReader, Write},
-};
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Result;
 use ore_boost_api::state::Stake;
-use solana_sdk::{address_lookup_table, instruction::Instruction, pubkey::Pubkey, signer::Signer};
+use solana_sdk::{
+    address_lookup_table::{self, AddressLookupTableAccount},
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signer::Signer,
+};
 
 use crate::{
-    client::{AsyncClient, Client},
-    error::Error::InvalidPubkeyBytes,
+    client::{self, Client},
+    error::Error::{InstructionTooLarge, UnwarmedLookupTable},
+    registry::{self, Entry, Registry, Status},
+    stakes::StakeIndex,
 };
 
 const MAX_ACCOUNTS_PER_LUT: usize = 256;
 
+/// slots since its derivation before an empty table is claimed as an orphan,
+/// well past the slot hashes a create may reference
+/// and the settle before its first extend
+const ORPHAN_MARGIN_SLOTS: u64 = 1_500;
+
 type LookupTables = Vec<Pubkey>;
 type StakeAccounts = Vec<(Pubkey, Stake)>;
+
+/// lookup tables, keyed by the stake addresses they hold
+#[derive(Debug, Default)]
+pub struct Index {
+    lookup_tables: HashMap<Pubkey, AddressLookupTableAccount>,
+    addresses: HashMap<Pubkey, Pubkey>,
+}
+
+impl Index {
+    /// if an address is held by more than one table, the first wins
+    pub fn new(lookup_tables: Vec<AddressLookupTableAccount>) -> Self {
+        let mut index = Self::default();
+        for lut in lookup_tables {
+            for address in lut.addresses.iter() {
+                index.addresses.entry(*address).or_insert(lut.key);
+            }
+            index.lookup_tables.insert(lut.key, lut);
+        }
+        index
+    }
+    /// lookup table holding the address
+    pub fn get(&self, address: &Pubkey) -> Option<&AddressLookupTableAccount> {
+        self.addresses
+            .get(address)
+            .and_then(|lut| self.lookup_tables.get(lut))
+    }
+}
+
 /// sync lookup tables
 ///
 /// add and/or extend lookup tables
 /// for new stake accounts for next checkpoint
-pub async fn sync(client: &Client, boost: &Pubkey) -> Result<(LookupTables, StakeAccounts)> {
+///
+/// in a dry run the create and extend transactions are only simulated,
+/// and new tables are left out of the registry
+pub async fn sync(
+    client: &Client,
+    boost: &Pubkey,
+    stakes: &StakeIndex,
+) -> Result<(LookupTables, StakeAccounts)> {
     log::info!("{} -- syncing lookup tables", boost);
-    // read existing lookup table addresses
-    let existing = read_file(boost)?;
-    log::info!("{} -- existing lookup tables: {:?}", boost, existing);
-    // fetch lookup table accounts for the stake addresses they hold
-    let lookup_tables = client.rpc.get_lookup_tables(existing.as_slice()).await?;
-    // fetch all stake accounts
-    let stake_accounts = client.rpc.get_boost_stake_accounts(boost).await?;
+    // read all stake accounts from the index
+    let stake_accounts = stakes.stake_accounts(client).await?;
+    // read existing lookup table addresses,
+    // recovering from chain if the registry was lost
+    let mut registry = match read_registry(client, boost)? {
+        Some(registry) => registry,
+        None => recover(client, boost, stake_accounts.as_slice()).await?,
+    };
+    let registered = registry.addresses();
+    log::info!("{} -- existing lookup tables: {:?}", boost, registered);
+    // fetch lookup table accounts for the stake addresses they hold,
+    // dropping tables that are gone so their stake accounts are tabled again
+    let fetched = client
+        .rpc
+        .get_multiple_lookup_tables(registered.as_slice())
+        .await?;
+    let mut lookup_tables = vec![];
+    for (address, lut) in registered.iter().zip(fetched) {
+        match lut {
+            Some(lut) => lookup_tables.push(lut),
+            None => {
+                log::warn!(
+                    "{} -- lookup table {} is missing or closed, dropping from registry",
+                    boost,
+                    address
+                );
+                registry.remove(address);
+            }
+        }
+    }
+    if lookup_tables.len().lt(&registered.len()) {
+        write_registry(client, &registry)?;
+    }
+    let existing = registry.addresses();
     // filter for stake accounts that don't already have a lookup table
     let tabled_stake_account_addresses = lookup_tables
         .iter()
@@ -85,20 +157,390 @@ pub async fn sync(client: &Client, boost: &Pubkey) -> Result<(LookupTables, Stak
     if !rest.is_empty() {
         for chunk in rest.chunks(MAX_ACCOUNTS_PER_LUT) {
             // allocate new lookup table
-            let lut_pda = create_lookup_table(client, boost).await?;
-            write_file(&[lut_pda], boost)?;
-            log::info!(
-                "{} -- sleeping to allow lookup table creation to settle",
-                boost
-            );
-            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+            let (lut_pda, slot) = create_lookup_table(client, boost).await?;
+            // in a dry run the table was never created,
+            // so is left out of the registry
+            if !client.config.dry_run {
+                registry.insert(Entry {
+                    address: lut_pda,
+                    boost: *boost,
+                    slot: Some(slot),
+                    authority: client.keypair.pubkey(),
+                    status: Status::Active,
+                });
+                write_registry(client, &registry)?;
+                log::info!(
+                    "{} -- sleeping to allow lookup table creation to settle",
+                    boost
+                );
+                tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+            }
             // extend this new lookup table
             extend_lookup_table(client, boost, &lut_pda, chunk).await?;
         }
     }
-    // read latest lookup tables
-    let lookup_tables = read_file(boost)?;
-    Ok((lookup_tables, stake_accounts))
+    Ok((registry.addresses(), stake_accounts))
+}
+
+/// index stake addresses by the lookup table holding them
+pub async fn index(client: &Client, lookup_tables: &[Pubkey]) -> Result<Index> {
+    let lookup_tables = client.rpc.get_lookup_tables(lookup_tables).await?;
+    Ok(Index::new(lookup_tables))
+}
+
+/// rebuild the registry from lookup tables on chain
+///
+/// registers every lookup table owned by the worker keypair
+/// that holds stake accounts of this boost,
+/// and every orphaned table whose addresses are all closed, so gc reclaims its rent.
+/// tables holding accounts still open elsewhere belong to another boost and are skipped.
+/// deactivated tables are registered as such, pending close.
+/// an orphan is claimed by each boost that recovers it,
+/// the first to close it wins and the rest drop it once missing.
+/// empty tables derived within the orphan margin may still be settling
+/// before another boost extends them, so are left alone.
+async fn recover(
+    client: &Client,
+    boost: &Pubkey,
+    stake_accounts: &[(Pubkey, Stake)],
+) -> Result<Registry> {
+    log::info!("{} -- recovering lookup table registry from chain", boost);
+    let authority = client.keypair.pubkey();
+    let owned = client
+        .rpc
+        .get_lookup_tables_by_authority(&authority)
+        .await?;
+    let clock = client.rpc.get_clock().await?;
+    let stake_addresses = stake_accounts
+        .iter()
+        .map(|(pubkey, _)| *pubkey)
+        .collect::<HashSet<_>>();
+    let mut registry = Registry::new(boost);
+    for (lut, deactivation_slot) in owned {
+        let live = lut
+            .addresses
+            .iter()
+            .any(|address| stake_addresses.contains(address));
+        let status = match deactivation_slot {
+            Some(slot) => Status::Deactivated { slot },
+            None if live => Status::Active,
+            None => Status::Retired,
+        };
+        if !live {
+            let exist = client.rpc.accounts_exist(lut.addresses.as_slice()).await?;
+            if exist.iter().any(|exists| *exists) {
+                continue;
+            }
+            if lut.addresses.is_empty() && recently_derived(&authority, &lut.key, clock.slot) {
+                continue;
+            }
+            log::info!("{} -- recovering orphaned lookup table {}", boost, lut.key);
+        }
+        registry.insert(Entry {
+            address: lut.key,
+            boost: *boost,
+            slot: None,
+            authority,
+            status,
+        });
+    }
+    log::info!(
+        "{} -- recovered {} lookup tables",
+        boost,
+        registry.lookup_tables.len()
+    );
+    write_registry(client, &registry)?;
+    Ok(registry)
+}
+
+/// whether the table was derived from a slot within the orphan margin
+fn recently_derived(authority: &Pubkey, address: &Pubkey, slot: u64) -> bool {
+    (slot.saturating_sub(ORPHAN_MARGIN_SLOTS)..=slot).any(|recent| {
+        address_lookup_table::instruction::derive_lookup_table_address(authority, recent)
+            .0
+            .eq(address)
+    })
+}
+
+/// registered lookup tables,
+/// each with the number of addresses it holds on chain, or none if missing
+pub async fn list(client: &Client, boost: &Pubkey) -> Result<Vec<(Entry, Option<usize>)>> {
+    let Some(registry) = read_registry(client, boost)? else {
+        return Ok(vec![]);
+    };
+    let addresses = registry
+        .lookup_tables
+        .iter()
+        .map(|lut| lut.address)
+        .collect::<Vec<_>>();
+    let fetched = client
+        .rpc
+        .get_multiple_lookup_tables(addresses.as_slice())
+        .await?;
+    Ok(registry
+        .lookup_tables
+        .into_iter()
+        .zip(fetched)
+        .map(|(entry, lut)| (entry, lut.map(|lut| lut.addresses.len())))
+        .collect())
+}
+
+/// slots a deactivated lookup table must cool down before it can be closed,
+/// until the deactivation slot leaves the slot hashes sysvar
+const DEACTIVATION_COOLDOWN_SLOTS: u64 = 513;
+
+/// outcome of one garbage collection pass
+#[derive(Debug, Default, PartialEq)]
+pub struct Collected {
+    pub deactivated: usize,
+    pub closed: usize,
+    pub lamports: u64,
+}
+
+/// garbage collect lookup tables
+///
+/// deactivate tables that no longer hold any live stake account,
+/// or every table if the boost has expired.
+/// close deactivated tables once their cooldown has elapsed,
+/// removing them from the registry and reclaiming rent.
+/// cooldowns span passes, so run repeatedly.
+pub async fn gc(
+    client: &Client,
+    boost: &Pubkey,
+    stake_accounts: &[(Pubkey, Stake)],
+) -> Result<Collected> {
+    let mut collected = Collected::default();
+    if client.config.dry_run {
+        log::info!("{} -- dry run, skipping lookup table collection", boost);
+        return Ok(collected);
+    }
+    let authority = client.keypair.pubkey();
+    let mut registry = match read_registry(client, boost)? {
+        Some(registry) if !registry.lookup_tables.is_empty() => registry,
+        _ => return Ok(collected),
+    };
+    log::info!("{} -- collecting lookup tables", boost);
+    let clock = client.rpc.get_clock().await?;
+    let expired = match client.rpc.get_boost(boost).await {
+        Ok(account) => account.expires_at.le(&clock.unix_timestamp),
+        // closed boosts have expired
+        Err(err) => match client.rpc.accounts_exist(&[*boost]).await?.as_slice() {
+            [false] => true,
+            _ => return Err(err),
+        },
+    };
+    let stake_addresses = stake_accounts
+        .iter()
+        .map(|(pubkey, _)| *pubkey)
+        .collect::<HashSet<_>>();
+    // retire obsolete tables,
+    // and drop tables already gone
+    let registered = registry.addresses();
+    let fetched = client
+        .rpc
+        .get_multiple_lookup_tables(registered.as_slice())
+        .await?;
+    for (address, lut) in registered.iter().zip(fetched) {
+        let Some(lut) = lut else {
+            log::warn!("{} -- lookup table {} is missing or closed", boost, address);
+            registry.remove(address);
+            continue;
+        };
+        let obsolete = expired || !lut.addresses.iter().any(|a| stake_addresses.contains(a));
+        if obsolete {
+            registry.set_status(address, Status::Retired);
+        }
+    }
+    // retire tables left staged by an interrupted compaction
+    for address in registry.with_status(|status| status.eq(&Status::Staged)) {
+        registry.set_status(&address, Status::Retired);
+    }
+    write_registry(client, &registry)?;
+    // deactivate retired tables
+    collected.deactivated = deactivate_retired(client, &mut registry).await?;
+    // close tables that have cooled down
+    let closable = registry.with_status(|status| match status {
+        Status::Deactivated { slot } => clock.slot > slot + DEACTIVATION_COOLDOWN_SLOTS,
+        _ => false,
+    });
+    for address in closable {
+        log::info!("{} -- closing lookup table {}", boost, address);
+        let lamports = client.rpc.get_balance(&address).await?;
+        let ix =
+            address_lookup_table::instruction::close_lookup_table(address, authority, authority);
+        if let Err(err) = client.send_transaction(&[ix]).await {
+            // still cooling down, retry next pass
+            log::error!("{} -- {:?}", boost, err);
+            continue;
+        }
+        registry.remove(&address);
+        write_registry(client, &registry)?;
+        collected.closed += 1;
+        collected.lamports += lamports;
+    }
+    log::info!(
+        "{} -- deactivated {} and closed {} lookup tables, recovered {} SOL",
+        boost,
+        collected.deactivated,
+        collected.closed,
+        collected.lamports as f64 / LAMPORTS_PER_SOL as f64
+    );
+    Ok(collected)
+}
+
+/// deactivate every retired table in the registry,
+/// returns the number deactivated
+async fn deactivate_retired(client: &Client, registry: &mut Registry) -> Result<usize> {
+    let authority = client.keypair.pubkey();
+    let mut deactivated = 0;
+    for address in registry.with_status(|status| status.eq(&Status::Retired)) {
+        log::info!(
+            "{} -- deactivating lookup table {}",
+            registry.boost,
+            address
+        );
+        let ix = address_lookup_table::instruction::deactivate_lookup_table(address, authority);
+        if let Err(err) = client.send_transaction(&[ix]).await {
+            // stays retired, retry next pass
+            log::error!("{} -- {:?}", registry.boost, err);
+            continue;
+        }
+        let clock = client.rpc.get_clock().await?;
+        registry.set_status(&address, Status::Deactivated { slot: clock.slot });
+        write_registry(client, registry)?;
+        deactivated += 1;
+    }
+    Ok(deactivated)
+}
+
+/// repack live stake addresses into the fewest fresh lookup tables
+///
+/// runs only if that saves at least one table.
+/// new tables are staged in the registry while they are created and extended,
+/// then swapped in with a single registry write once warm.
+/// the old tables are retired and deactivated, and gc closes them after their cooldown.
+/// returns true if compacted.
+pub async fn compact(
+    client: &Client,
+    boost: &Pubkey,
+    stake_accounts: &[(Pubkey, Stake)],
+) -> Result<bool> {
+    if client.config.dry_run {
+        log::info!("{} -- dry run, skipping lookup table compaction", boost);
+        return Ok(false);
+    }
+    log::info!("{} -- checking lookup tables for compaction", boost);
+    let authority = client.keypair.pubkey();
+    let Some(mut registry) = read_registry(client, boost)? else {
+        return Ok(false);
+    };
+    let old = registry.addresses();
+    let lookup_tables = client.rpc.get_lookup_tables(old.as_slice()).await?;
+    let stake_addresses = stake_accounts
+        .iter()
+        .map(|(pubkey, _)| *pubkey)
+        .collect::<HashSet<_>>();
+    // live addresses, in table order
+    let mut seen = HashSet::new();
+    let live = lookup_tables
+        .iter()
+        .flat_map(|lut| lut.addresses.iter())
+        .filter(|address| stake_addresses.contains(address) && seen.insert(**address))
+        .copied()
+        .collect::<Vec<_>>();
+    let needed = live.len().div_ceil(MAX_ACCOUNTS_PER_LUT);
+    if needed >= old.len() {
+        log::info!("{} -- lookup tables already compact", boost);
+        return Ok(false);
+    }
+    log::info!(
+        "{} -- compacting {} live addresses from {} lookup tables into {}",
+        boost,
+        live.len(),
+        old.len(),
+        needed
+    );
+    // stage fresh tables
+    let mut staged = vec![];
+    for chunk in live.chunks(MAX_ACCOUNTS_PER_LUT) {
+        let (lut_pda, slot) = create_lookup_table(client, boost).await?;
+        registry.insert(Entry {
+            address: lut_pda,
+            boost: *boost,
+            slot: Some(slot),
+            authority,
+            status: Status::Staged,
+        });
+        write_registry(client, &registry)?;
+        log::info!(
+            "{} -- sleeping to allow lookup table creation to settle",
+            boost
+        );
+        tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+        extend_lookup_table(client, boost, &lut_pda, chunk).await?;
+        staged.push((lut_pda, chunk.len()));
+    }
+    // wait until the staged tables hold every address
+    for (lut_pda, len) in staged.iter() {
+        let mut retries = 0;
+        loop {
+            let lut = client.rpc.get_lookup_table(lut_pda).await?;
+            if lut.addresses.len().ge(len) {
+                break;
+            }
+            retries += 1;
+            if retries == 10 {
+                return Err(anyhow::anyhow!(UnwarmedLookupTable));
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        }
+    }
+    // let the last extension slot pass before use
+    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+    // swap atomically
+    for (lut_pda, _) in staged.iter() {
+        registry.set_status(lut_pda, Status::Active);
+    }
+    for address in old.iter() {
+        registry.set_status(address, Status::Retired);
+    }
+    write_registry(client, &registry)?;
+    log::info!("{} -- swapped in compacted lookup tables", boost);
+    // schedule old tables for close
+    deactivate_retired(client, &mut registry).await?;
+    Ok(true)
+}
+
+/// extend instructions, each holding as many addresses as fit in one transaction
+fn extend_instructions(
+    signer: &Pubkey,
+    lookup_table: &Pubkey,
+    addresses: &[Pubkey],
+) -> Result<Vec<Instruction>> {
+    let extend = |chunk: &[Pubkey]| {
+        address_lookup_table::instruction::extend_lookup_table(
+            *lookup_table,
+            *signer,
+            Some(*signer),
+            chunk.to_vec(),
+        )
+    };
+    let mut ixs = vec![];
+    let mut start = 0;
+    for end in 1..=addresses.len() {
+        if client::fits_in_transaction(signer, &[extend(&addresses[start..end])], &[])? {
+            continue;
+        }
+        if end - 1 == start {
+            return Err(anyhow::anyhow!(InstructionTooLarge));
+        }
+        ixs.push(extend(&addresses[start..end - 1]));
+        start = end - 1;
+    }
+    if start < addresses.len() {
+        ixs.push(extend(&addresses[start..]));
+    }
+    Ok(ixs)
 }
 
 async fn extend_lookup_table(
@@ -109,19 +551,13 @@ async fn extend_lookup_table(
 ) -> Result<()> {
     log::info!("{:?} -- extending lookup table", boost);
     let mut bundles: Vec<Vec<Instruction>> = Vec::with_capacity(5);
-    for chunk in stake_accounts.chunks(26) {
-        let signer = client.keypair.pubkey();
-        let extend_ix = address_lookup_table::instruction::extend_lookup_table(
-            *lookup_table,
-            signer,
-            Some(signer),
-            chunk.to_vec(),
-        );
+    let signer = client.keypair.pubkey();
+    for extend_ix in extend_instructions(&signer, lookup_table, stake_accounts)? {
         bundles.push(vec![extend_ix]);
         if bundles.len().eq(&5) {
             let compiled: Vec<&[Instruction]> = bundles.iter().map(|vec| vec.as_slice()).collect();
             log::info!("{:?} -- sending extend instructions as bundle", boost);
-            client.send_jito_bundle(compiled.as_slice()).await?;
+            client.send_jito_bundle(boost, compiled.as_slice()).await?;
             bundles.clear();
         }
     }
@@ -130,91 +566,365 @@ async fn extend_lookup_table(
         log::info!("{:?} -- found left over extend bundles", boost);
         let compiled: Vec<&[Instruction]> = bundles.iter().map(|vec| vec.as_slice()).collect();
         log::info!("{:?} -- sending extend instructions as bundle", boost);
-        client.send_jito_bundle(compiled.as_slice()).await?;
+        client.send_jito_bundle(boost, compiled.as_slice()).await?;
     }
     Ok(())
 }
 
-async fn create_lookup_table(client: &Client, boost: &Pubkey) -> Result<Pubkey> {
+/// read the registry of the boost,
+/// leaving a legacy file unmigrated in a dry run
+fn read_registry(client: &Client, boost: &Pubkey) -> Result<Option<Registry>> {
+    registry::read(
+        client.config.luts_path.as_str(),
+        boost,
+        &client.keypair.pubkey(),
+        !client.config.dry_run,
+    )
+}
+
+/// persist the registry, unless a dry run
+fn write_registry(client: &Client, registry: &Registry) -> Result<()> {
+    if client.config.dry_run {
+        log::info!("{} -- dry run, not writing registry", registry.boost);
+        return Ok(());
+    }
+    registry::write(client.config.luts_path.as_str(), registry)
+}
+
+/// returns the new lookup table address and the slot it was derived from,
+/// only simulated in a dry run
+///
+/// every boost derives its tables from the same keypair and slot,
+/// so creates take turns, each on a slot after the last
+async fn create_lookup_table(client: &Client, boost: &Pubkey) -> Result<(Pubkey, u64)> {
     log::info!("{:?} -- opening new lookup table", boost);
-    let clock = client.rpc.get_clock().await?;
+    let mut last = client.lookup_table_slot.lock().await;
+    let mut clock = client.rpc.get_clock().await?;
+    while clock.slot.le(&last) {
+        tokio::time::sleep(tokio::time::Duration::from_millis(400)).await;
+        clock = client.rpc.get_clock().await?;
+    }
+    *last = clock.slot;
     let signer = client.keypair.pubkey();
     // build and submit create instruction first
     let (create_ix, lut_pda) =
         address_lookup_table::instruction::create_lookup_table(signer, signer, clock.slot);
     let sig = client.send_transaction(&[create_ix]).await?;
     log::info!("{:?} -- new lookup table signature: {:?}", boost, sig);
-    Ok(lut_pda)
-}
-
-fn write_file(luts: &[Lut], boost: &Pubkey) -> Result<()> {
-    log::info!("{:?} -- writing new lookup tables", boost);
-    let luts_path = luts_path()?;
-    let path = format!("{}-{}", luts_path, boost);
-    log::info!("path: {}", path);
-    let mut file = OpenOptions::new()
-        .create(true) // open or create
-        .append(true) // append
-        .open(path)?;
-    for lut in luts {
-        file.write_all(lut.to_bytes().as_slice())?;
-        file.write_all(b"\n")?;
-    }
-    log::info!("{:?} -- new lookup tables written", boost);
-    Ok(())
+    Ok((lut_pda, clock.slot))
 }
 
-type Lut = Pubkey;
-fn read_file(boost: &Pubkey) -> Result<Vec<Lut>> {
-    log::info!("{:?} -- reading prior lookup tables", boost);
-    let luts_path = luts_path()?;
-    let path = format!("{}-{}", luts_path, boost);
-    let file = match File::open(path.as_str()) {
-        Ok(f) => f,
-        Err(err) => match err.kind() {
-            std::io::ErrorKind::NotFound => {
-                log::info!(
-                    "{} -- no prior lookup tables found, creating new cache file",
-                    boost
-                );
-                let _file = File::create(path)?;
-                log::info!("{} -- new cache file created", boost);
-                return Ok(vec![]);
-            }
-            _ => {
-                return Err(anyhow::anyhow!(err));
-            }
-        },
-    };
-    log::info!("{:?} -- found prior lookup tables file", boost);
-    let mut luts = vec![];
-    let mut line = vec![];
-    let mut reader = BufReader::new(file);
-    // read lines
-    while reader.read_until(b'\n', &mut line)? > 0 {
-        // pop new line char
-        line.pop();
-        // decode
-        let bytes = line.clone();
-        let pubkey: Result<[u8; 32]> = bytes
-            .try_into()
-            .map_err(|_| anyhow::anyhow!(InvalidPubkeyBytes));
-        if let Ok(ref arr) = pubkey {
-            let pubkey = Pubkey::new_from_array(*arr);
-            // add pubkey to list
-            luts.push(pubkey);
-        };
-        if let Err(err) = pubkey {
-            log::error!("{:?}", err);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockChain;
+
+    async fn gc_indexed(client: &Client, boost: &Pubkey, stakes: &StakeIndex) -> Collected {
+        let stake_accounts = stakes.stake_accounts(client).await.unwrap();
+        gc(client, boost, stake_accounts.as_slice()).await.unwrap()
+    }
+
+    async fn compact_indexed(client: &Client, boost: &Pubkey, stakes: &StakeIndex) -> bool {
+        let stake_accounts = stakes.stake_accounts(client).await.unwrap();
+        compact(client, boost, stake_accounts.as_slice())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sync_tables_every_stake_account() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (_, boost) = chain.add_boost();
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        for _ in 0..300 {
+            chain.add_stake(&boost);
+        }
+        let (luts, stake_accounts) = sync(&client, &boost, &stakes).await.unwrap();
+        assert_eq!(stake_accounts.len(), 300);
+        assert_eq!(luts.len(), 2);
+        // new stakers extend the table with spare capacity
+        for _ in 0..10 {
+            chain.add_stake(&boost);
         }
-        // clear and read next line
-        line.clear();
+        let (luts_again, stake_accounts) = sync(&client, &boost, &stakes).await.unwrap();
+        assert_eq!(luts_again, luts);
+        let tabled = luts
+            .iter()
+            .flat_map(|lut| chain.lookup_table_addresses(lut))
+            .collect::<HashSet<_>>();
+        assert_eq!(tabled.len(), 310);
+        assert!(stake_accounts
+            .iter()
+            .all(|(pubkey, _)| tabled.contains(pubkey)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sync_recovers_lost_registry_from_chain() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (_, boost) = chain.add_boost();
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        for _ in 0..300 {
+            chain.add_stake(&boost);
+        }
+        let (luts, _) = sync(&client, &boost, &stakes).await.unwrap();
+        assert_eq!(chain.lookup_table_count(), 2);
+        // lose the local registry
+        std::fs::remove_file(format!("{}-{}.json", client.config.luts_path, boost)).unwrap();
+        let (recovered, _) = sync(&client, &boost, &stakes).await.unwrap();
+        assert_eq!(chain.lookup_table_count(), 2);
+        assert_eq!(
+            recovered.into_iter().collect::<HashSet<_>>(),
+            luts.into_iter().collect::<HashSet<_>>()
+        );
     }
-    log::info!("{:?} -- parsed prior lookup tables", boost);
-    Ok(luts)
-}
 
-fn luts_path() -> Result<String> {
-    let path = std::env::var("LUTS_PATH")?;
-    Ok(path)
+    #[tokio::test(start_paused = true)]
+    async fn recover_registers_deactivated_and_orphaned_tables() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (_, boost) = chain.add_boost();
+        let (_, other) = chain.add_boost();
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        let other_stakes = StakeIndex::new(&client, &other).await.unwrap();
+        for _ in 0..300 {
+            chain.add_stake(&boost);
+        }
+        for _ in 0..10 {
+            chain.add_stake(&other);
+        }
+        let (luts, _) = sync(&client, &boost, &stakes).await.unwrap();
+        let (other_luts, _) = sync(&client, &other, &other_stakes).await.unwrap();
+        assert_eq!(luts.len(), 2);
+        // deactivated but never closed
+        chain.deactivate_lookup_table(&luts[0]);
+        // every stake account of the table withdrawn
+        for stake in chain.lookup_table_addresses(&luts[1]) {
+            chain.close_stake(&stake);
+        }
+        let stake_accounts = client.rpc.get_boost_stake_accounts(&boost).await.unwrap();
+        let registry = recover(&client, &boost, stake_accounts.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(registry.lookup_tables.len(), 2);
+        assert_eq!(
+            registry.with_status(|status| matches!(status, Status::Deactivated { .. })),
+            vec![luts[0]]
+        );
+        assert_eq!(
+            registry.with_status(|status| status.eq(&Status::Retired)),
+            vec![luts[1]]
+        );
+        // the other boost keeps its table
+        assert!(registry
+            .lookup_tables
+            .iter()
+            .all(|lut| lut.address.ne(&other_luts[0])));
+        // orphan is deactivated on the next pass
+        let collected = gc(&client, &boost, stake_accounts.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(collected.deactivated, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn recover_leaves_settling_tables_alone() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (_, boost) = chain.add_boost();
+        let (_, other) = chain.add_boost();
+        // created by the other boost, not yet extended
+        let (settling, _) = create_lookup_table(&client, &other).await.unwrap();
+        let registry = recover(&client, &boost, &[]).await.unwrap();
+        assert!(registry.lookup_tables.is_empty());
+        // never extended, so orphaned
+        tokio::time::sleep(tokio::time::Duration::from_secs(ORPHAN_MARGIN_SLOTS)).await;
+        let registry = recover(&client, &boost, &[]).await.unwrap();
+        assert_eq!(
+            registry.with_status(|status| status.eq(&Status::Retired)),
+            vec![settling]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sync_drops_missing_tables() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (_, boost) = chain.add_boost();
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        for _ in 0..300 {
+            chain.add_stake(&boost);
+        }
+        let (luts, _) = sync(&client, &boost, &stakes).await.unwrap();
+        // missing tables are skipped, in request order
+        let missing = Pubkey::new_unique();
+        let fetched = client
+            .rpc
+            .get_lookup_tables(&[luts[1], missing, luts[0]])
+            .await
+            .unwrap();
+        assert_eq!(
+            fetched.iter().map(|lut| lut.key).collect::<Vec<_>>(),
+            vec![luts[1], luts[0]]
+        );
+        // closed out of band, its stake accounts are tabled again
+        chain.remove_lookup_table(&luts[1]);
+        let listed = list(&client, &boost).await.unwrap();
+        assert_eq!(listed.len(), luts.len());
+        assert!(listed
+            .iter()
+            .all(|(entry, len)| len.is_none().eq(&entry.address.eq(&luts[1]))));
+        let (synced, stake_accounts) = sync(&client, &boost, &stakes).await.unwrap();
+        assert!(!synced.contains(&luts[1]));
+        let tabled = synced
+            .iter()
+            .flat_map(|lut| chain.lookup_table_addresses(lut))
+            .collect::<HashSet<_>>();
+        assert!(stake_accounts
+            .iter()
+            .all(|(pubkey, _)| tabled.contains(pubkey)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sync_in_dry_run_creates_nothing() {
+        let chain = MockChain::new();
+        let client = chain.dry_run_client();
+        let (_, boost) = chain.add_boost();
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        for _ in 0..300 {
+            chain.add_stake(&boost);
+        }
+        let (luts, stake_accounts) = sync(&client, &boost, &stakes).await.unwrap();
+        assert_eq!(stake_accounts.len(), 300);
+        assert!(luts.is_empty());
+        assert_eq!(chain.lookup_table_count(), 0);
+        assert_eq!(chain.submissions(), (0, 0));
+        assert!(list(&client, &boost).await.unwrap().is_empty());
+        // nothing recovered or synced is written
+        assert!(
+            !std::path::Path::new(&format!("{}-{}.json", client.config.luts_path, boost)).exists()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gc_closes_tables_without_live_stake_accounts() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (_, boost) = chain.add_boost();
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        for _ in 0..300 {
+            chain.add_stake(&boost);
+        }
+        let (luts, _) = sync(&client, &boost, &stakes).await.unwrap();
+        // every staker in the second table withdraws
+        let (live, dead) = (luts[0], luts[1]);
+        for stake in chain.lookup_table_addresses(&dead) {
+            chain.close_stake(&stake);
+        }
+        let collected = gc_indexed(&client, &boost, &stakes).await;
+        assert_eq!(collected.deactivated, 1);
+        assert_eq!(collected.closed, 0);
+        let (luts, _) = sync(&client, &boost, &stakes).await.unwrap();
+        assert_eq!(luts, vec![live]);
+        // close once the cooldown has elapsed
+        tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
+        let collected = gc_indexed(&client, &boost, &stakes).await;
+        assert_eq!(collected.deactivated, 0);
+        assert_eq!(collected.closed, 1);
+        assert!(collected.lamports > 0);
+        assert_eq!(chain.lookup_table_count(), 1);
+        let registry = read_registry(&client, &boost).unwrap().unwrap();
+        assert_eq!(registry.addresses(), vec![live]);
+        assert_eq!(registry.lookup_tables.len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gc_closes_every_table_of_a_closed_boost() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (_, boost) = chain.add_boost();
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        for _ in 0..10 {
+            chain.add_stake(&boost);
+        }
+        sync(&client, &boost, &stakes).await.unwrap();
+        chain.remove_boost(&boost);
+        let collected = gc_indexed(&client, &boost, &stakes).await;
+        assert_eq!(collected.deactivated, 1);
+        tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
+        let collected = gc_indexed(&client, &boost, &stakes).await;
+        assert_eq!(collected.closed, 1);
+        assert_eq!(chain.lookup_table_count(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn concurrent_creates_derive_distinct_tables() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (_, boost) = chain.add_boost();
+        let (_, other) = chain.add_boost();
+        let (created, other_created) = tokio::join!(
+            create_lookup_table(&client, &boost),
+            create_lookup_table(&client, &other)
+        );
+        let ((lut, slot), (other_lut, other_slot)) = (created.unwrap(), other_created.unwrap());
+        assert_ne!(lut, other_lut);
+        assert_ne!(slot, other_slot);
+        assert_eq!(chain.lookup_table_count(), 2);
+    }
+
+    #[test]
+    fn extend_instructions_fill_transactions() {
+        let signer = Pubkey::new_unique();
+        let lookup_table = Pubkey::new_unique();
+        let addresses = (0..100).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+        let ixs = extend_instructions(&signer, &lookup_table, addresses.as_slice()).unwrap();
+        // every instruction fits, and one more address would not
+        for ix in ixs.iter() {
+            assert!(client::fits_in_transaction(&signer, &[ix.clone()], &[]).unwrap());
+        }
+        let first = &ixs[0];
+        let n = (first.data.len() - 12) / 32;
+        let overfull = address_lookup_table::instruction::extend_lookup_table(
+            lookup_table,
+            signer,
+            Some(signer),
+            addresses[..n + 1].to_vec(),
+        );
+        assert!(!client::fits_in_transaction(&signer, &[overfull], &[]).unwrap());
+        assert_eq!(ixs.len(), addresses.len().div_ceil(n));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn compact_repacks_live_addresses() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (_, boost) = chain.add_boost();
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        for _ in 0..300 {
+            chain.add_stake(&boost);
+        }
+        let (old, _) = sync(&client, &boost, &stakes).await.unwrap();
+        // a hundred stakers in the first table withdraw
+        for stake in chain.lookup_table_addresses(&old[0]).into_iter().take(100) {
+            chain.close_stake(&stake);
+        }
+        assert!(compact_indexed(&client, &boost, &stakes).await);
+        let (luts, stake_accounts) = sync(&client, &boost, &stakes).await.unwrap();
+        assert_eq!(luts.len(), 1);
+        assert!(!old.contains(&luts[0]));
+        let tabled = chain.lookup_table_addresses(&luts[0]);
+        assert_eq!(tabled.len(), 200);
+        assert!(stake_accounts
+            .iter()
+            .all(|(pubkey, _)| tabled.contains(pubkey)));
+        // already compact
+        assert!(!compact_indexed(&client, &boost, &stakes).await);
+        // old tables close after their cooldown
+        tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
+        let collected = gc_indexed(&client, &boost, &stakes).await;
+        assert_eq!(collected.closed, 2);
+        assert_eq!(chain.lookup_table_count(), 1);
+    }
 }
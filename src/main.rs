@@ -1,20 +1,38 @@
 mod checkpoint;
+mod cli;
 mod client;
+mod config;
 mod error;
+mod jito;
 mod lookup_tables;
+#[cfg(test)]
+mod mock;
+mod pubsub;
+mod registry;
+mod scheduler;
+mod stakes;
+mod submit;
+mod worker;
+
+use std::sync::Arc;
+
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(about = "rebases ore boost checkpoints")]
+struct Cli {
+    #[command(flatten)]
+    flags: config::Flags,
+    /// runs checkpoint loops if omitted
+    #[command(subcommand)]
+    command: Option<cli::Command>,
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     env_logger::init();
-    let mint = mint()?;
-    let client = client::Client::new()?;
-    checkpoint::run(&client, &mint).await?;
-    Ok(())
-}
-
-fn mint() -> anyhow::Result<solana_sdk::pubkey::Pubkey> {
-    let str = std::env::var("MINT")?;
-    use std::str::FromStr;
-    let pubkey = solana_sdk::pubkey::Pubkey::from_str(str.as_str())?;
-    Ok(pubkey)
+    let cli = Cli::parse();
+    let config = config::Config::load(&cli.flags)?;
+    let client = Arc::new(client::Client::new(config)?);
+    cli::run(client, cli.command.unwrap_or(cli::Command::Run)).await
 }
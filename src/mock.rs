@@ -0,0 +1,588 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytemuck::Zeroable;
+use ore_boost_api::consts::CHECKPOINT_INTERVAL;
+use ore_boost_api::state::{Boost, Checkpoint, Stake};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::address_lookup_table::instruction::ProgramInstruction;
+use solana_sdk::address_lookup_table::state::LOOKUP_TABLE_META_SIZE;
+use solana_sdk::address_lookup_table::{self, AddressLookupTableAccount};
+use solana_sdk::hash::Hash;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::transaction::{TransactionError, VersionedTransaction};
+use steel::Clock;
+
+use crate::client::{AsyncClient, Client, SendClient, Simulation};
+use crate::config::Config;
+use crate::jito::{Outcome, TipConfig, Tipper, Tracked};
+use crate::pubsub::SubscribeClient;
+use crate::stakes::Stakes;
+
+static LUTS_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// compute units reported per simulated instruction
+const SIMULATED_UNITS_PER_IX: u64 = 10_000;
+
+/// in-memory chain for end-to-end tests
+///
+/// holds boost, checkpoint, stake and lookup table accounts,
+/// and applies rebase and lookup table instructions
+/// from submitted transactions and bundles.
+/// the clock follows tokio time, so paused tests advance it for free.
+/// subscribers are woken on any rebase, and never notified of stake accounts.
+pub struct MockChain {
+    state: Mutex<State>,
+    rebased: tokio::sync::Notify,
+    start: tokio::time::Instant,
+    start_ts: i64,
+}
+
+#[derive(Clone, Default)]
+struct State {
+    /// keyed by boost address
+    boosts: HashMap<Pubkey, Boost>,
+    /// keyed by boost address
+    checkpoints: HashMap<Pubkey, Checkpoint>,
+    /// total stakers snapshot of the in-flight checkpoint, keyed by boost address
+    checkpoint_totals: HashMap<Pubkey, u64>,
+    /// completed checkpoints, keyed by boost address
+    checkpoints_completed: HashMap<Pubkey, u64>,
+    stakes: HashMap<Pubkey, Stake>,
+    /// times each stake account was rebased
+    rebases: HashMap<Pubkey, u64>,
+    lookup_tables: HashMap<Pubkey, LookupTable>,
+    transactions: u64,
+    bundles: u64,
+    /// upcoming bundles that will not land
+    dropped_bundles: u64,
+    /// upcoming bundles reported landed, then rolled back
+    rolled_back_bundles: u64,
+    /// signatures of applied transactions
+    signatures: HashSet<Signature>,
+    /// every simulation fails
+    failing_simulations: bool,
+}
+
+#[derive(Clone)]
+struct LookupTable {
+    authority: Pubkey,
+    addresses: Vec<Pubkey>,
+    deactivation_slot: Option<u64>,
+}
+
+impl MockChain {
+    pub fn new() -> Arc<Self> {
+        // every test in the process shares one fresh cache dir,
+        // boosts are unique per test
+        LUTS_DIR.get_or_init(|| {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos();
+            let luts_dir = std::env::temp_dir().join(format!(
+                "ore-boost-rebase-worker-tests-{}-{}",
+                std::process::id(),
+                nanos
+            ));
+            std::fs::create_dir_all(luts_dir.as_path()).unwrap();
+            luts_dir
+        });
+        Arc::new(Self {
+            state: Mutex::new(State::default()),
+            rebased: tokio::sync::Notify::new(),
+            start: tokio::time::Instant::now(),
+            start_ts: 1_700_000_000,
+        })
+    }
+    /// client backed by this chain with a fresh keypair
+    pub fn client(self: &Arc<Self>) -> Client {
+        let luts_path = LUTS_DIR.get().unwrap().join("luts");
+        let config = Config {
+            luts_path: luts_path.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        Client {
+            rpc: self.clone(),
+            sender: self.clone(),
+            keypair: Arc::new(Keypair::new()),
+            tipper: Arc::new(Tipper::new(
+                TipConfig {
+                    floor_url: None,
+                    ..Default::default()
+                },
+                HashMap::new(),
+            )),
+            config: Arc::new(config),
+            helius: None,
+            pubsub: None,
+            lookup_table_slot: tokio::sync::Mutex::new(0),
+        }
+    }
+    /// client subscribed to this chain
+    pub fn subscribed_client(self: &Arc<Self>) -> Client {
+        let mut client = self.client();
+        client.pubsub = Some(self.clone());
+        client
+    }
+    /// client that only simulates against this chain, never submitting
+    pub fn dry_run_client(self: &Arc<Self>) -> Client {
+        let mut client = self.client();
+        client.config = Arc::new(Config {
+            dry_run: true,
+            ..(*client.config).clone()
+        });
+        client
+    }
+    /// opens a boost for a new mint whose checkpoint interval has already elapsed,
+    /// returns (mint, boost address)
+    pub fn add_boost(&self) -> (Pubkey, Pubkey) {
+        let mint = Pubkey::new_unique();
+        let (boost_pda, _) = ore_boost_api::state::boost_pda(mint);
+        let mut boost = Boost::zeroed();
+        boost.mint = mint;
+        boost.expires_at = i64::MAX;
+        let mut checkpoint = Checkpoint::zeroed();
+        checkpoint.boost = boost_pda;
+        checkpoint.ts = self.now() - CHECKPOINT_INTERVAL;
+        let mut state = self.state.lock().unwrap();
+        state.boosts.insert(boost_pda, boost);
+        state.checkpoints.insert(boost_pda, checkpoint);
+        (mint, boost_pda)
+    }
+    /// opens a stake account with the next id, returns its address
+    pub fn add_stake(&self, boost: &Pubkey) -> Pubkey {
+        let address = Pubkey::new_unique();
+        let mut state = self.state.lock().unwrap();
+        let mut stake = Stake::zeroed();
+        stake.authority = Pubkey::new_unique();
+        stake.boost = *boost;
+        stake.id = state.stakes.values().filter(|s| s.boost.eq(boost)).count() as u64;
+        state.stakes.insert(address, stake);
+        address
+    }
+    pub fn checkpoint(&self, boost: &Pubkey) -> Checkpoint {
+        self.state.lock().unwrap().checkpoints[boost]
+    }
+    pub fn checkpoints_completed(&self, boost: &Pubkey) -> u64 {
+        let state = self.state.lock().unwrap();
+        state
+            .checkpoints_completed
+            .get(boost)
+            .copied()
+            .unwrap_or_default()
+    }
+    pub fn rebases(&self, stake: &Pubkey) -> u64 {
+        let state = self.state.lock().unwrap();
+        state.rebases.get(stake).copied().unwrap_or_default()
+    }
+    pub fn lookup_table_addresses(&self, lut: &Pubkey) -> Vec<Pubkey> {
+        self.state.lock().unwrap().lookup_tables[lut]
+            .addresses
+            .clone()
+    }
+    /// expires the boost as of now
+    pub fn expire_boost(&self, boost: &Pubkey) {
+        let now = self.now();
+        let mut state = self.state.lock().unwrap();
+        if let Some(boost) = state.boosts.get_mut(boost) {
+            boost.expires_at = now;
+        }
+    }
+    /// closes the boost and its checkpoint
+    pub fn remove_boost(&self, boost: &Pubkey) {
+        let mut state = self.state.lock().unwrap();
+        state.boosts.remove(boost);
+        state.checkpoints.remove(boost);
+    }
+    /// closes the stake account, as when a staker withdraws
+    pub fn close_stake(&self, address: &Pubkey) {
+        self.state.lock().unwrap().stakes.remove(address);
+    }
+    /// removes the lookup table, as if closed out of band
+    pub fn remove_lookup_table(&self, lut: &Pubkey) {
+        self.state.lock().unwrap().lookup_tables.remove(lut);
+    }
+    /// deactivates the lookup table, as if by an earlier run
+    pub fn deactivate_lookup_table(&self, lut: &Pubkey) {
+        let slot = self.slot();
+        if let Some(table) = self.state.lock().unwrap().lookup_tables.get_mut(lut) {
+            table.deactivation_slot = Some(slot);
+        }
+    }
+    pub fn lookup_table_count(&self) -> usize {
+        self.state.lock().unwrap().lookup_tables.len()
+    }
+    /// number of submitted (transactions, bundles)
+    pub fn submissions(&self) -> (u64, u64) {
+        let state = self.state.lock().unwrap();
+        (state.transactions, state.bundles)
+    }
+    /// the next n bundles fail to land
+    pub fn drop_bundles(&self, n: u64) {
+        self.state.lock().unwrap().dropped_bundles = n;
+    }
+    /// every simulation from now on fails
+    pub fn fail_simulations(&self) {
+        self.state.lock().unwrap().failing_simulations = true;
+    }
+    /// the next n bundles are reported landed, but rolled back as on a fork
+    pub fn roll_back_bundles(&self, n: u64) {
+        self.state.lock().unwrap().rolled_back_bundles = n;
+    }
+    pub fn now(&self) -> i64 {
+        self.start_ts + self.start.elapsed().as_secs() as i64
+    }
+    fn slot(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64 / 400
+    }
+    /// apply every instruction of the transaction, resolving lookup tables
+    fn apply(&self, state: &mut State, tx: &VersionedTransaction) -> Result<()> {
+        let size = bincode::serialized_size(tx)? as usize;
+        if size > PACKET_DATA_SIZE {
+            return Err(anyhow::anyhow!("transaction too large: {} bytes", size));
+        }
+        let message = &tx.message;
+        // static keys, then writable lookups, then readonly lookups
+        let mut keys = message.static_account_keys().to_vec();
+        let mut readonly = vec![];
+        for lookup in message.address_table_lookups().unwrap_or_default() {
+            let table = state
+                .lookup_tables
+                .get(&lookup.account_key)
+                .ok_or(anyhow::anyhow!("missing lookup table"))?;
+            for index in lookup.writable_indexes.iter() {
+                keys.push(table.addresses[*index as usize]);
+            }
+            for index in lookup.readonly_indexes.iter() {
+                readonly.push(table.addresses[*index as usize]);
+            }
+        }
+        keys.extend(readonly);
+        let rebase_data =
+            ore_boost_api::sdk::rebase(Pubkey::default(), Pubkey::default(), Pubkey::default())
+                .data;
+        for ix in message.instructions() {
+            let program_id = keys[ix.program_id_index as usize];
+            let accounts = ix
+                .accounts
+                .iter()
+                .map(|index| keys[*index as usize])
+                .collect::<Vec<_>>();
+            if program_id.eq(&ore_boost_api::ID) && ix.data.eq(&rebase_data) {
+                self.rebase(state, accounts.as_slice())?;
+            } else if program_id.eq(&address_lookup_table::program::ID) {
+                let ix = bincode::deserialize::<ProgramInstruction>(ix.data.as_slice())?;
+                Self::lookup_table(state, accounts.as_slice(), ix, self.slot())?;
+            }
+        }
+        state.signatures.insert(tx.signatures[0]);
+        Ok(())
+    }
+    /// stake accounts must be rebased in id order,
+    /// checkpoint resets once every staker is rebased
+    fn rebase(&self, state: &mut State, accounts: &[Pubkey]) -> Result<()> {
+        let boost = accounts
+            .iter()
+            .find(|a| state.boosts.contains_key(*a))
+            .copied()
+            .ok_or(anyhow::anyhow!("missing boost"))?;
+        let stake = accounts
+            .iter()
+            .find_map(|a| state.stakes.get(a).map(|stake| (*a, *stake)));
+        let total_stakers = state.stakes.values().filter(|s| s.boost.eq(&boost)).count() as u64;
+        let now = self.now();
+        let checkpoint = state.checkpoints.get_mut(&boost).unwrap();
+        if now - checkpoint.ts < CHECKPOINT_INTERVAL {
+            return Ok(());
+        }
+        // kickoff
+        if checkpoint.current_id.eq(&0) {
+            state.checkpoint_totals.insert(boost, total_stakers);
+        }
+        let total = state.checkpoint_totals[&boost];
+        if let Some((address, stake)) = stake {
+            if stake.id.ne(&checkpoint.current_id) {
+                return Err(anyhow::anyhow!(
+                    "stake id {} rebased out of order, current id {}",
+                    stake.id,
+                    checkpoint.current_id
+                ));
+            }
+            checkpoint.current_id += 1;
+            *state.rebases.entry(address).or_default() += 1;
+        }
+        // finalize
+        if checkpoint.current_id.ge(&total) {
+            checkpoint.current_id = 0;
+            checkpoint.ts = now;
+            *state.checkpoints_completed.entry(boost).or_default() += 1;
+        }
+        self.rebased.notify_waiters();
+        Ok(())
+    }
+    fn lookup_table(
+        state: &mut State,
+        accounts: &[Pubkey],
+        ix: ProgramInstruction,
+        slot: u64,
+    ) -> Result<()> {
+        let lut = accounts[0];
+        match ix {
+            ProgramInstruction::CreateLookupTable { .. } => {
+                let table = LookupTable {
+                    authority: accounts[1],
+                    addresses: vec![],
+                    deactivation_slot: None,
+                };
+                if state.lookup_tables.insert(lut, table).is_some() {
+                    return Err(anyhow::anyhow!("lookup table already exists"));
+                }
+            }
+            ProgramInstruction::ExtendLookupTable { new_addresses } => {
+                let table = state
+                    .lookup_tables
+                    .get_mut(&lut)
+                    .ok_or(anyhow::anyhow!("missing lookup table"))?;
+                table.addresses.extend(new_addresses);
+                if table.addresses.len().gt(&256) {
+                    return Err(anyhow::anyhow!("lookup table full"));
+                }
+            }
+            ProgramInstruction::DeactivateLookupTable => {
+                let table = state
+                    .lookup_tables
+                    .get_mut(&lut)
+                    .ok_or(anyhow::anyhow!("missing lookup table"))?;
+                table.deactivation_slot = Some(slot);
+            }
+            ProgramInstruction::CloseLookupTable => {
+                let table = state
+                    .lookup_tables
+                    .get(&lut)
+                    .ok_or(anyhow::anyhow!("missing lookup table"))?;
+                match table.deactivation_slot {
+                    Some(deactivated) if slot > deactivated + 512 => {
+                        state.lookup_tables.remove(&lut);
+                    }
+                    _ => return Err(anyhow::anyhow!("lookup table not fully deactivated")),
+                }
+            }
+            ProgramInstruction::FreezeLookupTable => {}
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AsyncClient for MockChain {
+    fn get_async_client(&self) -> Result<&RpcClient> {
+        Err(anyhow::anyhow!("mock chain has no rpc client"))
+    }
+    async fn get_boost(&self, boost: &Pubkey) -> Result<Boost> {
+        let state = self.state.lock().unwrap();
+        let boost = state
+            .boosts
+            .get(boost)
+            .copied()
+            .ok_or(anyhow::anyhow!("missing boost"))?;
+        Ok(boost)
+    }
+    async fn get_boosts(&self) -> Result<Vec<(Pubkey, Boost)>> {
+        let state = self.state.lock().unwrap();
+        Ok(state.boosts.iter().map(|(k, v)| (*k, *v)).collect())
+    }
+    async fn get_boost_stake_accounts(&self, boost: &Pubkey) -> Result<Vec<(Pubkey, Stake)>> {
+        let state = self.state.lock().unwrap();
+        let accounts = state
+            .stakes
+            .iter()
+            .filter(|(_, stake)| stake.boost.eq(boost))
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        Ok(accounts)
+    }
+    async fn get_checkpoint(&self, checkpoint: &Pubkey) -> Result<Checkpoint> {
+        let state = self.state.lock().unwrap();
+        let checkpoint = state
+            .checkpoints
+            .iter()
+            .find(|(boost, _)| {
+                ore_boost_api::state::checkpoint_pda(**boost)
+                    .0
+                    .eq(checkpoint)
+            })
+            .map(|(_, checkpoint)| *checkpoint)
+            .ok_or(anyhow::anyhow!("missing checkpoint"))?;
+        Ok(checkpoint)
+    }
+    async fn get_clock(&self) -> Result<Clock> {
+        let clock = Clock {
+            slot: self.slot(),
+            unix_timestamp: self.now(),
+            ..Default::default()
+        };
+        Ok(clock)
+    }
+    async fn signatures_confirmed(&self, sigs: &[Signature]) -> Result<bool> {
+        let state = self.state.lock().unwrap();
+        Ok(!sigs.is_empty() && sigs.iter().all(|sig| state.signatures.contains(sig)))
+    }
+    async fn get_lookup_table(&self, lut: &Pubkey) -> Result<AddressLookupTableAccount> {
+        let state = self.state.lock().unwrap();
+        let addresses = state
+            .lookup_tables
+            .get(lut)
+            .map(|table| table.addresses.clone())
+            .ok_or(anyhow::anyhow!("missing lookup table"))?;
+        Ok(AddressLookupTableAccount {
+            key: *lut,
+            addresses,
+        })
+    }
+    async fn get_multiple_lookup_tables(
+        &self,
+        luts: &[Pubkey],
+    ) -> Result<Vec<Option<AddressLookupTableAccount>>> {
+        let state = self.state.lock().unwrap();
+        let accounts = luts
+            .iter()
+            .map(|lut| {
+                state
+                    .lookup_tables
+                    .get(lut)
+                    .map(|table| AddressLookupTableAccount {
+                        key: *lut,
+                        addresses: table.addresses.clone(),
+                    })
+            })
+            .collect();
+        Ok(accounts)
+    }
+    async fn get_lookup_tables_by_authority(
+        &self,
+        authority: &Pubkey,
+    ) -> Result<Vec<(AddressLookupTableAccount, Option<u64>)>> {
+        let state = self.state.lock().unwrap();
+        let accounts = state
+            .lookup_tables
+            .iter()
+            .filter(|(_, table)| table.authority.eq(authority))
+            .map(|(key, table)| {
+                let lut = AddressLookupTableAccount {
+                    key: *key,
+                    addresses: table.addresses.clone(),
+                };
+                (lut, table.deactivation_slot)
+            })
+            .collect();
+        Ok(accounts)
+    }
+    async fn accounts_exist(&self, addresses: &[Pubkey]) -> Result<Vec<bool>> {
+        let state = self.state.lock().unwrap();
+        let exist = addresses
+            .iter()
+            .map(|address| {
+                state.stakes.contains_key(address)
+                    || state.boosts.contains_key(address)
+                    || state.lookup_tables.contains_key(address)
+            })
+            .collect();
+        Ok(exist)
+    }
+    async fn get_balance(&self, pubkey: &Pubkey) -> Result<u64> {
+        let state = self.state.lock().unwrap();
+        let lamports = state
+            .lookup_tables
+            .get(pubkey)
+            .map(|table| {
+                Rent::default().minimum_balance(LOOKUP_TABLE_META_SIZE + 32 * table.addresses.len())
+            })
+            .unwrap_or_default();
+        Ok(lamports)
+    }
+    async fn get_latest_blockhash(&self) -> Result<Hash> {
+        Ok(Hash::new_unique())
+    }
+    async fn get_recent_priority_fee(&self, _accounts: &[Pubkey]) -> Result<u64> {
+        Ok(0)
+    }
+    async fn simulate_transaction(&self, tx: &VersionedTransaction) -> Result<Simulation> {
+        let units = tx.message.instructions().len() as u64 * SIMULATED_UNITS_PER_IX;
+        let failing = self.state.lock().unwrap().failing_simulations;
+        Ok(Simulation {
+            err: failing.then_some(TransactionError::AccountNotFound),
+            logs: vec![],
+            units_consumed: Some(units),
+        })
+    }
+}
+
+#[async_trait]
+impl SendClient for MockChain {
+    async fn send_transaction(&self, tx: &VersionedTransaction) -> Result<Signature> {
+        let mut state = self.state.lock().unwrap();
+        self.apply(&mut state, tx)?;
+        state.transactions += 1;
+        Ok(tx.signatures[0])
+    }
+    async fn send_transactions(&self, txs: &[VersionedTransaction]) -> Result<Vec<Signature>> {
+        let mut sigs = vec![];
+        for tx in txs {
+            sigs.push(self.send_transaction(tx).await?);
+        }
+        Ok(sigs)
+    }
+    async fn send_bundle(&self, txs: &[VersionedTransaction]) -> Result<Tracked> {
+        let mut state = self.state.lock().unwrap();
+        if state.dropped_bundles.gt(&0) {
+            state.dropped_bundles -= 1;
+            return Ok(Tracked {
+                bundle_id: "dropped".to_string(),
+                outcome: Outcome::Expired,
+            });
+        }
+        if state.rolled_back_bundles.gt(&0) {
+            state.rolled_back_bundles -= 1;
+            return Ok(Tracked {
+                bundle_id: "rolled-back".to_string(),
+                outcome: Outcome::Landed {
+                    slot: Some(self.slot()),
+                    signatures: txs.iter().map(|tx| tx.signatures[0]).collect(),
+                },
+            });
+        }
+        // bundles land atomically
+        let mut next = state.clone();
+        for tx in txs {
+            self.apply(&mut next, tx)?;
+        }
+        next.transactions += txs.len() as u64;
+        next.bundles += 1;
+        *state = next;
+        Ok(Tracked {
+            bundle_id: format!("bundle-{}", state.bundles),
+            outcome: Outcome::Landed {
+                slot: Some(self.slot()),
+                signatures: txs.iter().map(|tx| tx.signatures[0]).collect(),
+            },
+        })
+    }
+}
+
+#[async_trait]
+impl SubscribeClient for MockChain {
+    async fn account_changed(&self, _address: &Pubkey) -> Result<()> {
+        self.rebased.notified().await;
+        Ok(())
+    }
+    async fn notify_stakes(&self, _boost: &Pubkey, _stakes: &Stakes) -> Result<()> {
+        std::future::pending().await
+    }
+}
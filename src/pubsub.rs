@@ -0,0 +1,151 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use ore_boost_api::state::Stake;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::pubsub_client::{PubsubClient, UnsubscribeFn};
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
+use steel::{AccountDeserialize, Discriminator};
+use tokio::sync::Mutex;
+
+use crate::client::STAKE_BOOST_OFFSET;
+use crate::error::Error::SubscriptionClosed;
+use crate::stakes::{self, Stakes};
+
+/// account change notifications
+#[async_trait]
+pub trait SubscribeClient: Send + Sync {
+    /// resolves on the next change to the account
+    async fn account_changed(&self, address: &Pubkey) -> Result<()>;
+    /// apply stake account notifications of the boost to the index,
+    /// until the subscription closes
+    async fn notify_stakes(&self, boost: &Pubkey, stakes: &Stakes) -> Result<()>;
+}
+
+/// one websocket shared by every subscription in the process
+///
+/// connected on first subscribe,
+/// and reconnected by the next subscribe once a subscription on it fails
+pub struct Pubsub {
+    ws_url: String,
+    client: Mutex<Option<Arc<PubsubClient>>>,
+}
+
+impl Pubsub {
+    pub fn new(ws_url: String) -> Self {
+        Self {
+            ws_url,
+            client: Mutex::new(None),
+        }
+    }
+    /// the shared websocket, connecting if there is none
+    async fn connect(&self) -> Result<Arc<PubsubClient>> {
+        let mut client = self.client.lock().await;
+        if let Some(client) = client.as_ref() {
+            return Ok(client.clone());
+        }
+        log::info!("connecting to websocket: {}", self.ws_url);
+        let connected = Arc::new(PubsubClient::new(self.ws_url.as_str()).await?);
+        *client = Some(connected.clone());
+        Ok(connected)
+    }
+    /// drop the websocket a subscription failed on,
+    /// unless it was already replaced
+    async fn disconnect(&self, failed: &Arc<PubsubClient>) {
+        let mut client = self.client.lock().await;
+        if client
+            .as_ref()
+            .is_some_and(|client| Arc::ptr_eq(client, failed))
+        {
+            *client = None;
+        }
+    }
+}
+
+#[async_trait]
+impl SubscribeClient for Pubsub {
+    async fn account_changed(&self, address: &Pubkey) -> Result<()> {
+        let client = self.connect().await?;
+        let result = account_changed(client.as_ref(), address).await;
+        if result.is_err() {
+            self.disconnect(&client).await;
+        }
+        result
+    }
+    async fn notify_stakes(&self, boost: &Pubkey, stakes: &Stakes) -> Result<()> {
+        let client = self.connect().await?;
+        let result = notify_stakes(client.as_ref(), boost, stakes).await;
+        self.disconnect(&client).await;
+        result
+    }
+}
+
+/// unsubscribes once dropped,
+/// including when the subscription is dropped mid await
+struct Unsubscribe(Option<UnsubscribeFn>);
+
+impl Drop for Unsubscribe {
+    fn drop(&mut self) {
+        if let Some(unsubscribe) = self.0.take() {
+            tokio::spawn(unsubscribe());
+        }
+    }
+}
+
+async fn account_changed(client: &PubsubClient, address: &Pubkey) -> Result<()> {
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        ..Default::default()
+    };
+    let (mut notifications, unsubscribe) = client.account_subscribe(address, Some(config)).await?;
+    let _unsubscribe = Unsubscribe(Some(unsubscribe));
+    notifications
+        .next()
+        .await
+        .map(|_| ())
+        .ok_or(anyhow::anyhow!(SubscriptionClosed))
+}
+
+/// only returns once the subscription fails or closes
+async fn notify_stakes(client: &PubsubClient, boost: &Pubkey, stakes: &Stakes) -> Result<()> {
+    let filters = vec![
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            0,
+            Stake::discriminator().to_le_bytes().to_vec(),
+        )),
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            STAKE_BOOST_OFFSET,
+            boost.to_bytes().to_vec(),
+        )),
+    ];
+    let config = RpcProgramAccountsConfig {
+        filters: Some(filters),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(CommitmentConfig::confirmed()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let (mut notifications, unsubscribe) = client
+        .program_subscribe(&ore_boost_api::ID, Some(config))
+        .await?;
+    let _unsubscribe = Unsubscribe(Some(unsubscribe));
+    log::info!("{:?} -- subscribed to stake accounts", boost);
+    while let Some(notification) = notifications.next().await {
+        let keyed = notification.value;
+        let address = Pubkey::from_str(keyed.pubkey.as_str())?;
+        let stake = keyed
+            .account
+            .decode::<Account>()
+            .and_then(|account| Stake::try_from_bytes(account.data.as_slice()).ok().copied());
+        stakes::apply(stakes, &address, stake);
+    }
+    Err(anyhow::anyhow!(SubscriptionClosed))
+}
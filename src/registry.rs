@@ -0,0 +1,321 @@
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::PathBuf,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::Error::{InvalidPubkeyBytes, UnsupportedRegistryVersion};
+
+/// current on-disk registry version
+const VERSION: u32 = 1;
+
+/// lookup tables owned by the worker for one boost
+///
+/// persisted as json at `<luts_path>-<boost>.json`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Registry {
+    pub version: u32,
+    #[serde(with = "pubkey_string")]
+    pub boost: Pubkey,
+    pub lookup_tables: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    #[serde(with = "pubkey_string")]
+    pub address: Pubkey,
+    #[serde(with = "pubkey_string")]
+    pub boost: Pubkey,
+    /// recent slot the table was derived from,
+    /// unknown for tables migrated from the legacy format
+    #[serde(default)]
+    pub slot: Option<u64>,
+    #[serde(with = "pubkey_string")]
+    pub authority: Pubkey,
+    #[serde(default)]
+    pub status: Status,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// used by rebase transactions
+    #[default]
+    Active,
+    /// being filled by compaction, not yet used
+    Staged,
+    /// replaced or obsolete, pending deactivation
+    Retired,
+    /// deactivated at slot, pending close
+    Deactivated { slot: u64 },
+}
+
+impl Registry {
+    pub fn new(boost: &Pubkey) -> Self {
+        Self {
+            version: VERSION,
+            boost: *boost,
+            lookup_tables: vec![],
+        }
+    }
+    /// addresses of active lookup tables
+    pub fn addresses(&self) -> Vec<Pubkey> {
+        self.with_status(|status| status.eq(&Status::Active))
+    }
+    /// addresses of lookup tables whose status matches
+    pub fn with_status(&self, f: impl Fn(&Status) -> bool) -> Vec<Pubkey> {
+        self.lookup_tables
+            .iter()
+            .filter(|lut| f(&lut.status))
+            .map(|lut| lut.address)
+            .collect()
+    }
+    pub fn set_status(&mut self, address: &Pubkey, status: Status) {
+        if let Some(lut) = self
+            .lookup_tables
+            .iter_mut()
+            .find(|lut| lut.address.eq(address))
+        {
+            lut.status = status;
+        }
+    }
+    pub fn insert(&mut self, entry: Entry) {
+        if !self
+            .lookup_tables
+            .iter()
+            .any(|lut| lut.address.eq(&entry.address))
+        {
+            self.lookup_tables.push(entry);
+        }
+    }
+    pub fn remove(&mut self, address: &Pubkey) {
+        self.lookup_tables.retain(|lut| lut.address.ne(address));
+    }
+}
+
+/// read the registry for a boost
+///
+/// migrates the legacy newline format on first read,
+/// writing the registry and moving the legacy file aside only if persist is set.
+/// returns none if neither file exists
+pub fn read(
+    luts_path: &str,
+    boost: &Pubkey,
+    authority: &Pubkey,
+    persist: bool,
+) -> Result<Option<Registry>> {
+    log::info!("{:?} -- reading lookup table registry", boost);
+    let path = registry_path(luts_path, boost);
+    match File::open(path.as_path()) {
+        Ok(mut file) => {
+            let mut json = String::new();
+            file.read_to_string(&mut json)?;
+            let registry: Registry = serde_json::from_str(json.as_str())?;
+            if registry.version.ne(&VERSION) {
+                return Err(anyhow::anyhow!(UnsupportedRegistryVersion(
+                    registry.version
+                )));
+            }
+            log::info!(
+                "{:?} -- found {} registered lookup tables",
+                boost,
+                registry.lookup_tables.len()
+            );
+            Ok(Some(registry))
+        }
+        Err(err) if err.kind().eq(&std::io::ErrorKind::NotFound) => {
+            let legacy_path = legacy_path(luts_path, boost);
+            match std::fs::read(legacy_path.as_path()) {
+                Ok(bytes) => {
+                    log::info!("{:?} -- migrating legacy lookup tables file", boost);
+                    let luts = parse_legacy(bytes.as_slice())?;
+                    let mut registry = Registry::new(boost);
+                    for lut in luts {
+                        registry.insert(Entry {
+                            address: lut,
+                            boost: *boost,
+                            slot: None,
+                            authority: *authority,
+                            status: Status::Active,
+                        });
+                    }
+                    if !persist {
+                        return Ok(Some(registry));
+                    }
+                    write(luts_path, &registry)?;
+                    // keep the legacy file around, but out of the way
+                    let mut migrated = legacy_path.clone().into_os_string();
+                    migrated.push(".migrated");
+                    std::fs::rename(legacy_path, migrated)?;
+                    Ok(Some(registry))
+                }
+                Err(err) if err.kind().eq(&std::io::ErrorKind::NotFound) => {
+                    log::info!("{:?} -- no prior lookup tables found", boost);
+                    Ok(None)
+                }
+                Err(err) => Err(anyhow::anyhow!(err)),
+            }
+        }
+        Err(err) => Err(anyhow::anyhow!(err)),
+    }
+}
+
+/// atomically replace the registry on disk
+pub fn write(luts_path: &str, registry: &Registry) -> Result<()> {
+    log::info!("{:?} -- writing lookup table registry", registry.boost);
+    let path = registry_path(luts_path, &registry.boost);
+    let mut tmp = path.clone().into_os_string();
+    tmp.push(".tmp");
+    let json = serde_json::to_string_pretty(registry)?;
+    let mut file = File::create(tmp.as_os_str())?;
+    file.write_all(json.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(tmp, path)?;
+    log::info!("{:?} -- lookup table registry written", registry.boost);
+    Ok(())
+}
+
+/// legacy format is raw 32 byte pubkeys each terminated by a newline.
+/// parse fixed width records, since pubkeys may contain the newline byte.
+fn parse_legacy(bytes: &[u8]) -> Result<Vec<Pubkey>> {
+    let record = 33;
+    if bytes.len() % record != 0 {
+        return Err(anyhow::anyhow!(InvalidPubkeyBytes));
+    }
+    let mut luts = vec![];
+    for chunk in bytes.chunks(record) {
+        let (pubkey, newline) = chunk.split_at(32);
+        if newline.ne(b"\n") {
+            return Err(anyhow::anyhow!(InvalidPubkeyBytes));
+        }
+        let pubkey: [u8; 32] = pubkey
+            .try_into()
+            .map_err(|_| anyhow::anyhow!(InvalidPubkeyBytes))?;
+        luts.push(Pubkey::new_from_array(pubkey));
+    }
+    Ok(luts)
+}
+
+fn registry_path(luts_path: &str, boost: &Pubkey) -> PathBuf {
+    PathBuf::from(format!("{}-{}.json", luts_path, boost))
+}
+
+fn legacy_path(luts_path: &str, boost: &Pubkey) -> PathBuf {
+    PathBuf::from(format!("{}-{}", luts_path, boost))
+}
+
+mod pubkey_string {
+    use std::str::FromStr;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+    use solana_sdk::pubkey::Pubkey;
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(pubkey.to_string().as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        let str = String::deserialize(deserializer)?;
+        Pubkey::from_str(str.as_str()).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_luts_path(name: &str) -> String {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!(
+            "ore-boost-rebase-worker-registry-{}-{}",
+            std::process::id(),
+            nanos
+        ));
+        std::fs::create_dir_all(dir.as_path()).unwrap();
+        dir.join(name).to_string_lossy().to_string()
+    }
+
+    /// pubkey containing the legacy newline separator
+    fn newline_pubkey() -> Pubkey {
+        let mut bytes = Pubkey::new_unique().to_bytes();
+        bytes[7] = b'\n';
+        bytes[31] = b'\n';
+        Pubkey::new_from_array(bytes)
+    }
+
+    #[test]
+    fn registry_round_trips() {
+        let luts_path = test_luts_path("round-trip");
+        let boost = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        assert!(read(luts_path.as_str(), &boost, &authority, true)
+            .unwrap()
+            .is_none());
+        let mut registry = Registry::new(&boost);
+        let entry = Entry {
+            address: newline_pubkey(),
+            boost,
+            slot: Some(42),
+            authority,
+            status: Status::Active,
+        };
+        registry.insert(entry.clone());
+        registry.insert(entry);
+        write(luts_path.as_str(), &registry).unwrap();
+        let read = read(luts_path.as_str(), &boost, &authority, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(read, registry);
+        assert_eq!(read.lookup_tables.len(), 1);
+    }
+
+    #[test]
+    fn legacy_file_migrates() {
+        let luts_path = test_luts_path("legacy");
+        let boost = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let luts = [newline_pubkey(), Pubkey::new_unique(), newline_pubkey()];
+        let mut legacy = vec![];
+        for lut in luts.iter() {
+            legacy.extend_from_slice(lut.as_ref());
+            legacy.push(b'\n');
+        }
+        std::fs::write(legacy_path(luts_path.as_str(), &boost), legacy).unwrap();
+        // left in place unless persisted
+        let unpersisted = read(luts_path.as_str(), &boost, &authority, false)
+            .unwrap()
+            .unwrap();
+        assert!(legacy_path(luts_path.as_str(), &boost).exists());
+        assert!(!registry_path(luts_path.as_str(), &boost).exists());
+        let registry = read(luts_path.as_str(), &boost, &authority, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(registry.addresses(), luts.to_vec());
+        assert_eq!(unpersisted, registry);
+        assert!(registry
+            .lookup_tables
+            .iter()
+            .all(|lut| lut.boost.eq(&boost) && lut.authority.eq(&authority)));
+        // migrated once, legacy file moved aside
+        assert!(!legacy_path(luts_path.as_str(), &boost).exists());
+        let registry_again = read(luts_path.as_str(), &boost, &authority, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(registry_again, registry);
+    }
+
+    #[test]
+    fn truncated_legacy_file_is_rejected() {
+        let mut legacy = Pubkey::new_unique().to_bytes().to_vec();
+        legacy.push(b'\n');
+        legacy.extend_from_slice(&[1, 2, 3]);
+        assert!(parse_legacy(legacy.as_slice()).is_err());
+    }
+}
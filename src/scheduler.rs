@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use rand::Rng;
+use solana_sdk::pubkey::Pubkey;
+use tokio::time::Duration;
+
+use crate::pubsub::SubscribeClient;
+
+/// seconds past the deadline before firing,
+/// so the cluster clock has passed it too
+pub const MARGIN_SECS: u64 = 2;
+
+/// max random seconds added past the margin,
+/// so boosts sharing a deadline do not all fire at once
+pub const MAX_JITTER_SECS: u64 = 5;
+
+/// sleeps until the next checkpoint is due
+pub struct Scheduler {
+    /// wakes early on checkpoint account changes
+    pubsub: Option<Arc<dyn SubscribeClient>>,
+    margin: u64,
+    jitter: u64,
+}
+
+impl Scheduler {
+    pub fn new(pubsub: Option<Arc<dyn SubscribeClient>>, margin: u64, jitter: u64) -> Self {
+        Self {
+            pubsub,
+            margin,
+            jitter,
+        }
+    }
+    /// sleep until the remaining seconds have elapsed, past the margin and jitter,
+    /// or until the checkpoint account changes if subscribed
+    pub async fn wait(&self, boost: &Pubkey, checkpoint: &Pubkey, remaining: u64) {
+        let delay = delay(remaining, self.margin, self.jitter);
+        log::info!(
+            "{:?} -- sleeping {} seconds until next checkpoint",
+            boost,
+            delay.as_secs()
+        );
+        let Some(pubsub) = self.pubsub.as_ref() else {
+            tokio::time::sleep(delay).await;
+            return;
+        };
+        let changed = async {
+            match pubsub.account_changed(checkpoint).await {
+                Ok(()) => log::info!("{:?} -- checkpoint account changed", boost),
+                Err(err) => {
+                    // fall back to the deadline
+                    log::warn!("{:?} -- checkpoint subscription failed: {:?}", boost, err);
+                    std::future::pending::<()>().await
+                }
+            }
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = changed => {}
+        }
+    }
+}
+
+/// remaining seconds past the margin and a random jitter
+fn delay(remaining: u64, margin: u64, jitter: u64) -> Duration {
+    let jitter = rand::thread_rng().gen_range(0..=jitter);
+    Duration::from_secs(remaining + margin + jitter)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::signer::Signer;
+    use tokio::time::Instant;
+
+    use super::*;
+    use crate::mock::MockChain;
+
+    #[test]
+    fn delay_stays_within_margin_and_jitter() {
+        for _ in 0..100 {
+            let delay = delay(60, MARGIN_SECS, MAX_JITTER_SECS).as_secs();
+            assert!(delay >= 60 + MARGIN_SECS);
+            assert!(delay <= 60 + MARGIN_SECS + MAX_JITTER_SECS);
+        }
+        assert_eq!(delay(0, 0, 0), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_wakes_on_checkpoint_change() {
+        let chain = MockChain::new();
+        let client = chain.subscribed_client();
+        let (mint, boost) = chain.add_boost();
+        let (checkpoint, _) = ore_boost_api::state::checkpoint_pda(boost);
+        let scheduler = Scheduler::new(client.pubsub.clone(), 0, 0);
+        // rebased ten seconds in, well before the deadline
+        let start = Instant::now();
+        let rebase = async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            let ix = ore_boost_api::sdk::rebase(client.keypair.pubkey(), mint, Pubkey::default());
+            client.send_transaction(&[ix]).await.unwrap();
+        };
+        tokio::join!(scheduler.wait(&boost, &checkpoint, 60), rebase);
+        assert_eq!(start.elapsed().as_secs(), 10);
+        // unsubscribed, sleeps until the deadline
+        let scheduler = Scheduler::new(None, 0, 0);
+        let start = Instant::now();
+        scheduler.wait(&boost, &checkpoint, 60).await;
+        assert_eq!(start.elapsed().as_secs(), 60);
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use ore_boost_api::state::Stake;
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+
+use crate::client::{AsyncClient, Client};
+use crate::pubsub::SubscribeClient;
+
+/// seconds between full reconciliations of a subscribed index
+const RECONCILE_SECS: u64 = 600;
+
+/// seconds before resubscribing after the websocket fails,
+/// or retrying a failed reconciliation
+const RESUBSCRIBE_SECS: u64 = 5;
+
+pub type Stakes = Arc<Mutex<HashMap<Pubkey, Stake>>>;
+
+/// stake accounts of one boost, kept in memory
+///
+/// bootstrapped with a full scan,
+/// then kept current by program account notifications if subscribed to a websocket.
+/// closed accounts no longer match the subscription filters, so are never notified,
+/// and notifications are missed while resubscribing,
+/// so a subscribed index is reconciled with a full scan in the background,
+/// periodically and after each resubscribe.
+/// without a websocket every read is a full scan.
+pub struct StakeIndex {
+    boost: Pubkey,
+    stakes: Stakes,
+    /// notification and reconciliation tasks, if subscribed
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl StakeIndex {
+    pub async fn new(client: &Client, boost: &Pubkey) -> Result<Self> {
+        let stakes: Stakes = Arc::new(Mutex::new(HashMap::new()));
+        // subscribe before the bootstrap scan, so no change falls in between
+        let mut tasks = vec![];
+        if let Some(pubsub) = client.pubsub.clone() {
+            let resubscribed = Arc::new(Notify::new());
+            tasks.push(tokio::spawn(subscribe(
+                pubsub,
+                *boost,
+                stakes.clone(),
+                resubscribed.clone(),
+            )));
+            tasks.push(tokio::spawn(reconcile_periodically(
+                client.rpc.clone(),
+                *boost,
+                stakes.clone(),
+                resubscribed,
+            )));
+        }
+        let index = Self {
+            boost: *boost,
+            stakes,
+            tasks,
+        };
+        index.reconcile(client).await?;
+        Ok(index)
+    }
+    /// stake accounts in id order,
+    /// scanned first if not subscribed
+    pub async fn stake_accounts(&self, client: &Client) -> Result<Vec<(Pubkey, Stake)>> {
+        if self.tasks.is_empty() {
+            self.reconcile(client).await?;
+        }
+        let mut stake_accounts = self
+            .stakes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(address, stake)| (*address, *stake))
+            .collect::<Vec<_>>();
+        stake_accounts.sort_by_key(|(_, stake)| stake.id);
+        Ok(stake_accounts)
+    }
+    /// replace the index with a full scan now,
+    /// as when it is known to be missing stake accounts
+    pub async fn reconcile(&self, client: &Client) -> Result<()> {
+        reconcile(client.rpc.as_ref(), &self.boost, &self.stakes).await
+    }
+}
+
+impl Drop for StakeIndex {
+    fn drop(&mut self) {
+        for task in self.tasks.iter() {
+            task.abort();
+        }
+    }
+}
+
+/// replace the index with a full scan,
+/// keeping accounts notified while the scan was in flight
+async fn reconcile(rpc: &dyn AsyncClient, boost: &Pubkey, stakes: &Stakes) -> Result<()> {
+    log::info!("{:?} -- reconciling stake index", boost);
+    let before = stakes
+        .lock()
+        .unwrap()
+        .keys()
+        .copied()
+        .collect::<HashSet<_>>();
+    let scanned = rpc.get_boost_stake_accounts(boost).await?;
+    let mut stakes = stakes.lock().unwrap();
+    let live = scanned
+        .iter()
+        .map(|(address, _)| *address)
+        .collect::<HashSet<_>>();
+    for closed in before.difference(&live) {
+        stakes.remove(closed);
+    }
+    stakes.extend(scanned);
+    log::info!("{:?} -- indexed {} stake accounts", boost, stakes.len());
+    Ok(())
+}
+
+/// reconcile every interval, and soon after each resubscribe,
+/// retrying failed scans
+async fn reconcile_periodically(
+    rpc: Arc<dyn AsyncClient>,
+    boost: Pubkey,
+    stakes: Stakes,
+    resubscribed: Arc<Notify>,
+) {
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(RECONCILE_SECS)) => {}
+            _ = resubscribed.notified() => {}
+        }
+        while let Err(err) = reconcile(rpc.as_ref(), &boost, &stakes).await {
+            log::warn!("{:?} -- stake reconciliation failed: {:?}", boost, err);
+            tokio::time::sleep(Duration::from_secs(RESUBSCRIBE_SECS)).await;
+        }
+    }
+}
+
+/// apply program account notifications to the index,
+/// resubscribing whenever the websocket fails
+async fn subscribe(
+    pubsub: Arc<dyn SubscribeClient>,
+    boost: Pubkey,
+    stakes: Stakes,
+    resubscribed: Arc<Notify>,
+) {
+    loop {
+        if let Err(err) = pubsub.notify_stakes(&boost, &stakes).await {
+            log::warn!("{:?} -- stake subscription failed: {:?}", boost, err);
+        }
+        tokio::time::sleep(Duration::from_secs(RESUBSCRIBE_SECS)).await;
+        // notifications may have been missed
+        resubscribed.notify_one();
+    }
+}
+
+/// upsert the notified stake account, or remove it if no longer a stake
+pub fn apply(stakes: &Stakes, address: &Pubkey, stake: Option<Stake>) {
+    let mut stakes = stakes.lock().unwrap();
+    match stake {
+        Some(stake) => {
+            stakes.insert(*address, stake);
+        }
+        None => {
+            stakes.remove(address);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockChain;
+
+    #[tokio::test(start_paused = true)]
+    async fn index_tracks_stake_accounts() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (_, boost) = chain.add_boost();
+        let stakes = (0..3).map(|_| chain.add_stake(&boost)).collect::<Vec<_>>();
+        let index = StakeIndex::new(&client, &boost).await.unwrap();
+        let indexed = index.stake_accounts(&client).await.unwrap();
+        assert_eq!(
+            indexed
+                .iter()
+                .map(|(address, _)| *address)
+                .collect::<Vec<_>>(),
+            stakes
+        );
+        // unsubscribed, so every read reconciles
+        let added = chain.add_stake(&boost);
+        chain.close_stake(&stakes[0]);
+        let indexed = index.stake_accounts(&client).await.unwrap();
+        assert_eq!(
+            indexed
+                .iter()
+                .map(|(address, _)| *address)
+                .collect::<Vec<_>>(),
+            vec![stakes[1], stakes[2], added]
+        );
+        // notifications upsert and remove
+        let (_, stake) = indexed[0];
+        let notified = Pubkey::new_unique();
+        apply(&index.stakes, &notified, Some(stake));
+        apply(&index.stakes, &stakes[1], None);
+        let keys = index
+            .stakes
+            .lock()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect::<HashSet<_>>();
+        assert_eq!(keys, HashSet::from([notified, stakes[2], added]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn subscribed_index_reconciles_in_the_background() {
+        let chain = MockChain::new();
+        let client = chain.subscribed_client();
+        let (_, boost) = chain.add_boost();
+        let first = chain.add_stake(&boost);
+        let index = StakeIndex::new(&client, &boost).await.unwrap();
+        // reads do not scan, and the mock never notifies
+        let added = chain.add_stake(&boost);
+        let indexed = index.stake_accounts(&client).await.unwrap();
+        assert_eq!(indexed.len(), 1);
+        // picked up by the next reconciliation
+        tokio::time::sleep(Duration::from_secs(RECONCILE_SECS + 1)).await;
+        let indexed = index.stake_accounts(&client).await.unwrap();
+        assert_eq!(
+            indexed
+                .iter()
+                .map(|(address, _)| *address)
+                .collect::<Vec<_>>(),
+            vec![first, added]
+        );
+    }
+}
@@ -0,0 +1,338 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Signature};
+
+use crate::client::Client;
+use crate::error::Error::{InvalidJitoBundle, InvalidSubmitStrategy, UnconfirmedJitoBundle};
+use crate::jito::{Bundle, Outcome};
+
+/// consecutive failures of the primary strategy before falling back
+const MAX_FAILURES: u32 = 3;
+
+/// sends of a bundle before giving up on it
+const MAX_BUNDLE_ATTEMPTS: u32 = 2;
+
+/// age past which a prebuilt bundle is rebuilt,
+/// well within the blockhash lifetime
+const MAX_PREBUILT_SECS: u64 = 30;
+
+/// how rebase transactions are submitted
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String")]
+pub enum Strategy {
+    /// jito bundle, landing every transaction atomically
+    Jito,
+    /// rpc send transaction with priority fees, confirmed together
+    Rpc,
+    /// helius smart transactions,
+    /// each simulated for compute and priority fee by helius,
+    /// then sent and confirmed before the next
+    Helius,
+}
+
+impl FromStr for Strategy {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "jito" => Ok(Self::Jito),
+            "rpc" => Ok(Self::Rpc),
+            "helius" => Ok(Self::Helius),
+            _ => Err(anyhow::anyhow!(InvalidSubmitStrategy(s.to_string()))),
+        }
+    }
+}
+
+impl TryFrom<String> for Strategy {
+    type Error = anyhow::Error;
+    fn try_from(s: String) -> Result<Self> {
+        Self::from_str(s.as_str())
+    }
+}
+
+/// submits rebase transactions for one boost
+///
+/// falls back to a second strategy
+/// after the primary fails repeatedly, until reset
+pub struct Submitter {
+    boost: Pubkey,
+    primary: Strategy,
+    fallback: Option<Strategy>,
+    failures: u32,
+    /// a bundle went unlanded, raising the tip,
+    /// since the next was prebuilt
+    raised: bool,
+}
+
+impl Submitter {
+    pub fn new(boost: &Pubkey, primary: Strategy, fallback: Option<Strategy>) -> Self {
+        Self {
+            boost: *boost,
+            primary,
+            fallback,
+            failures: 0,
+            raised: false,
+        }
+    }
+    /// strategy for the next submission
+    pub fn strategy(&self) -> Strategy {
+        match self.fallback {
+            Some(fallback) if self.failures.ge(&MAX_FAILURES) => fallback,
+            _ => self.primary,
+        }
+    }
+    /// submit transactions in order, each with its lookup tables,
+    /// sending the prebuilt bundle first if still fresh and its tip current.
+    /// returns the landed signatures where known
+    pub async fn submit(
+        &mut self,
+        client: &Client,
+        transactions: &[(&[Instruction], &[Pubkey])],
+        prebuilt: Option<Bundle>,
+    ) -> Result<Vec<Signature>> {
+        let strategy = self.strategy();
+        let result = match strategy {
+            Strategy::Jito => self.submit_bundle(client, transactions, prebuilt).await,
+            Strategy::Rpc => client
+                .send_transactions_with_luts(transactions)
+                .await
+                .inspect(|sigs| {
+                    log::info!(
+                        "{:?} -- confirmed rebase signatures: {:?}",
+                        self.boost,
+                        sigs
+                    );
+                }),
+            Strategy::Helius => client
+                .send_smart_transactions_with_luts(transactions)
+                .await
+                .inspect(|sigs| {
+                    log::info!(
+                        "{:?} -- confirmed rebase signatures: {:?}",
+                        self.boost,
+                        sigs
+                    );
+                }),
+        };
+        if strategy.eq(&self.primary) {
+            match result {
+                Ok(_) => self.failures = 0,
+                Err(_) => {
+                    self.failures += 1;
+                    if let Some(fallback) =
+                        self.fallback.filter(|_| self.failures.eq(&MAX_FAILURES))
+                    {
+                        log::warn!(
+                            "{:?} -- {:?} failed {} times, falling back to {:?}",
+                            self.boost,
+                            self.primary,
+                            self.failures,
+                            fallback
+                        );
+                    }
+                }
+            }
+        }
+        result
+    }
+    /// send as a jito bundle,
+    /// rebuilding and resending if it fails or expires
+    async fn submit_bundle(
+        &mut self,
+        client: &Client,
+        transactions: &[(&[Instruction], &[Pubkey])],
+        prebuilt: Option<Bundle>,
+    ) -> Result<Vec<Signature>> {
+        let mut prebuilt =
+            prebuilt.filter(|bundle| bundle.built.elapsed().as_secs().lt(&MAX_PREBUILT_SECS));
+        // tipped before the last outcome was reported
+        if self.raised && prebuilt.take().is_some() {
+            log::info!("{:?} -- tip raised, rebuilding prebuilt bundle", self.boost);
+        }
+        self.raised = false;
+        for _ in 0..MAX_BUNDLE_ATTEMPTS {
+            let bundle = match prebuilt.take() {
+                Some(bundle) => bundle,
+                None => {
+                    client
+                        .build_jito_bundle_with_luts(&self.boost, transactions)
+                        .await?
+                }
+            };
+            if client.config.dry_run {
+                client
+                    .dry_run(bundle.transactions.as_slice(), bundle.tip.lamports)
+                    .await?;
+                return Ok(bundle
+                    .transactions
+                    .iter()
+                    .map(|tx| tx.signatures[0])
+                    .collect());
+            }
+            let tracked = client.send_built_jito_bundle(bundle).await?;
+            match tracked.outcome {
+                Outcome::Landed { slot, signatures } => {
+                    log::info!(
+                        "{:?} -- confirmed rebase bundle id: {:?} at slot {:?}",
+                        self.boost,
+                        tracked.bundle_id,
+                        slot
+                    );
+                    return Ok(signatures);
+                }
+                Outcome::Failed | Outcome::Expired => {
+                    self.raised = true;
+                    log::info!(
+                        "{:?} -- rebase bundle {:?} {:?}, rebuilding",
+                        self.boost,
+                        tracked.bundle_id,
+                        tracked.outcome
+                    );
+                }
+                Outcome::Invalid => return Err(anyhow::anyhow!(InvalidJitoBundle)),
+            }
+        }
+        Err(anyhow::anyhow!(UnconfirmedJitoBundle))
+    }
+    /// back to the primary strategy
+    pub fn reset(&mut self) {
+        self.failures = 0;
+    }
+}
+
+/// sign the bundle ahead of time if the strategy sends bundles
+///
+/// other strategies size compute by simulating against the landed state,
+/// so are built when submitted.
+/// returns none if the bundle could not be built, to be built when submitted instead.
+pub async fn prebuild(
+    client: &Client,
+    boost: &Pubkey,
+    strategy: Strategy,
+    transactions: &[(&[Instruction], &[Pubkey])],
+) -> Option<Bundle> {
+    if strategy.ne(&Strategy::Jito) {
+        return None;
+    }
+    match client
+        .build_jito_bundle_with_luts(boost, transactions)
+        .await
+    {
+        Ok(bundle) => Some(bundle),
+        Err(err) => {
+            log::warn!("{:?} -- failed to prebuild bundle: {:?}", boost, err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::signer::Signer;
+
+    use super::*;
+    use crate::mock::MockChain;
+
+    #[tokio::test(start_paused = true)]
+    async fn falls_back_after_unlanded_bundles() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        let stake_accounts = (0..4).map(|_| chain.add_stake(&boost)).collect::<Vec<_>>();
+        let ixs = stake_accounts
+            .iter()
+            .map(|stake| ore_boost_api::sdk::rebase(client.keypair.pubkey(), mint, *stake))
+            .collect::<Vec<_>>();
+        let mut submitter = Submitter::new(&boost, Strategy::Jito, Some(Strategy::Rpc));
+        chain.drop_bundles((MAX_FAILURES * MAX_BUNDLE_ATTEMPTS) as u64);
+        for _ in 0..MAX_FAILURES {
+            assert_eq!(submitter.strategy(), Strategy::Jito);
+            assert!(submitter
+                .submit(&client, &[(&ixs[..1], &[])], None)
+                .await
+                .is_err());
+        }
+        // falls back to rpc
+        assert_eq!(submitter.strategy(), Strategy::Rpc);
+        submitter
+            .submit(&client, &[(&ixs[..2], &[]), (&ixs[2..], &[])], None)
+            .await
+            .unwrap();
+        assert_eq!(chain.submissions(), (2, 0));
+        assert_eq!(chain.checkpoints_completed(&boost), 1);
+        // back to jito on reset
+        submitter.reset();
+        assert_eq!(submitter.strategy(), Strategy::Jito);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sends_prebuilt_bundle_while_fresh() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        let stake_accounts = (0..2).map(|_| chain.add_stake(&boost)).collect::<Vec<_>>();
+        let ixs = stake_accounts
+            .iter()
+            .map(|stake| ore_boost_api::sdk::rebase(client.keypair.pubkey(), mint, *stake))
+            .collect::<Vec<_>>();
+        let mut submitter = Submitter::new(&boost, Strategy::Jito, None);
+        // fresh bundle is sent as built
+        let first: &[(&[Instruction], &[Pubkey])] = &[(&ixs[..1], &[])];
+        let bundle = prebuild(&client, &boost, Strategy::Jito, first)
+            .await
+            .unwrap();
+        let prebuilt = bundle.transactions[0].signatures.clone();
+        let sigs = submitter
+            .submit(&client, first, Some(bundle))
+            .await
+            .unwrap();
+        assert_eq!(sigs, prebuilt);
+        // stale bundle is rebuilt
+        let second: &[(&[Instruction], &[Pubkey])] = &[(&ixs[1..], &[])];
+        let bundle = prebuild(&client, &boost, Strategy::Jito, second)
+            .await
+            .unwrap();
+        let prebuilt = bundle.transactions[0].signatures.clone();
+        tokio::time::sleep(tokio::time::Duration::from_secs(MAX_PREBUILT_SECS)).await;
+        let sigs = submitter
+            .submit(&client, second, Some(bundle))
+            .await
+            .unwrap();
+        assert_ne!(sigs, prebuilt);
+        assert_eq!(chain.submissions(), (2, 2));
+        // only bundles are prebuilt
+        assert!(prebuild(&client, &boost, Strategy::Rpc, second)
+            .await
+            .is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rebuilds_prebuilt_bundle_after_raised_tip() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (mint, boost) = chain.add_boost();
+        let stake_accounts = (0..2).map(|_| chain.add_stake(&boost)).collect::<Vec<_>>();
+        let ixs = stake_accounts
+            .iter()
+            .map(|stake| ore_boost_api::sdk::rebase(client.keypair.pubkey(), mint, *stake))
+            .collect::<Vec<_>>();
+        let mut submitter = Submitter::new(&boost, Strategy::Jito, None);
+        let first: &[(&[Instruction], &[Pubkey])] = &[(&ixs[..1], &[])];
+        let second: &[(&[Instruction], &[Pubkey])] = &[(&ixs[1..], &[])];
+        // prebuilt while the first is in flight
+        let bundle = prebuild(&client, &boost, Strategy::Jito, second)
+            .await
+            .unwrap();
+        let prebuilt = bundle.transactions[0].signatures.clone();
+        // which expires before landing
+        chain.drop_bundles(1);
+        submitter.submit(&client, first, None).await.unwrap();
+        // rebuilt at the raised tip
+        let sigs = submitter
+            .submit(&client, second, Some(bundle))
+            .await
+            .unwrap();
+        assert_ne!(sigs, prebuilt);
+        assert_eq!(chain.submissions(), (2, 2));
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use solana_sdk::pubkey::Pubkey;
+use tokio::task::JoinHandle;
+
+use crate::checkpoint;
+use crate::client::Client;
+use crate::lookup_tables;
+
+/// initial backoff after a boost fails
+const MIN_BACKOFF_SECS: u64 = 10;
+/// max backoff after repeated failures
+const MAX_BACKOFF_SECS: u64 = 300;
+/// how often to enumerate boosts on chain
+const DISCOVERY_INTERVAL_SECS: u64 = 300;
+
+/// run checkpoint loops for many boosts in one process
+///
+/// boosts are enumerated on chain every discovery interval.
+/// a checkpoint loop is started for each new boost
+/// and stopped once the boost expires or is closed.
+/// if mints are provided, only those boosts are run.
+///
+/// each boost is driven by its own task,
+/// so an error or panic in one boost never stalls the others
+pub async fn run(client: Arc<Client>, mints: Option<Vec<Pubkey>>) {
+    let mut workers: HashMap<Pubkey, JoinHandle<()>> = HashMap::new();
+    loop {
+        match discover(client.as_ref(), mints.as_deref()).await {
+            Ok((active, expired)) => {
+                reconcile(&client, &mut workers, active.as_slice());
+                // checkpoint loops of expired boosts are stopped,
+                // so their lookup tables are collected here
+                if client.config.luts.gc {
+                    for boost in expired {
+                        if let Err(err) = gc(client.as_ref(), &boost).await {
+                            log::error!("{:?} -- {:?}", boost, err);
+                        }
+                    }
+                }
+            }
+            Err(err) => log::error!("boost discovery failed: {:?}", err),
+        }
+        tokio::time::sleep(tokio::time::Duration::from_secs(DISCOVERY_INTERVAL_SECS)).await;
+    }
+}
+
+/// collect the lookup tables of an expired boost without a checkpoint loop
+///
+/// every table of an expired boost is obsolete,
+/// so its stake accounts are never scanned,
+/// and boosts with nothing left registered cost no rpc at all
+async fn gc(client: &Client, boost: &Pubkey) -> anyhow::Result<()> {
+    lookup_tables::gc(client, boost, &[]).await?;
+    Ok(())
+}
+
+/// mints of all boosts that are live on chain,
+/// and addresses of all boosts that have expired,
+/// narrowed to the configured mints if any
+async fn discover(
+    client: &Client,
+    mints: Option<&[Pubkey]>,
+) -> anyhow::Result<(Vec<Pubkey>, Vec<Pubkey>)> {
+    let boosts = client.rpc.get_boosts().await?;
+    let clock = client.rpc.get_clock().await?;
+    let (active, expired): (Vec<_>, Vec<_>) = boosts
+        .into_iter()
+        .filter(|(_, boost)| mints.is_none_or(|mints| mints.contains(&boost.mint)))
+        .partition(|(_, boost)| boost.expires_at.gt(&clock.unix_timestamp));
+    let active = active
+        .into_iter()
+        .map(|(_, boost)| boost.mint)
+        .collect::<Vec<_>>();
+    let expired = expired
+        .into_iter()
+        .map(|(address, _)| address)
+        .collect::<Vec<_>>();
+    log::info!(
+        "discovered {} active and {} expired boosts",
+        active.len(),
+        expired.len()
+    );
+    Ok((active, expired))
+}
+
+/// start workers for new boosts and stop workers for expired boosts
+fn reconcile(
+    client: &Arc<Client>,
+    workers: &mut HashMap<Pubkey, JoinHandle<()>>,
+    active: &[Pubkey],
+) {
+    // stop
+    workers.retain(|mint, handle| {
+        if active.contains(mint) {
+            return true;
+        }
+        log::info!(
+            "{:?} -- boost expired or closed, stopping checkpoint loop",
+            mint
+        );
+        handle.abort();
+        false
+    });
+    // start
+    for mint in active {
+        if workers.contains_key(mint) {
+            continue;
+        }
+        log::info!("{:?} -- new boost, starting checkpoint loop", mint);
+        let handle = tokio::spawn(supervise(Arc::clone(client), *mint));
+        workers.insert(*mint, handle);
+    }
+}
+
+/// run the checkpoint loop for one boost,
+/// restarting with backoff whenever it exits
+async fn supervise(client: Arc<Client>, mint: Pubkey) {
+    let mut backoff = MIN_BACKOFF_SECS;
+    loop {
+        let started = tokio::time::Instant::now();
+        // spawn so that a panic is caught here instead of taking down the worker
+        // and aborted if this supervisor is aborted
+        let mut handle = AbortOnDrop({
+            let client = Arc::clone(&client);
+            tokio::spawn(async move { checkpoint::run(client.as_ref(), &mint).await })
+        });
+        match (&mut handle.0).await {
+            Ok(Ok(())) => {
+                log::info!("{:?} -- checkpoint loop exited", mint);
+            }
+            Ok(Err(err)) => {
+                log::error!("{:?} -- checkpoint loop failed: {:?}", mint, err);
+            }
+            Err(err) => {
+                log::error!("{:?} -- checkpoint loop panicked: {:?}", mint, err);
+            }
+        }
+        // reset backoff if the loop was healthy for a while
+        if started.elapsed().as_secs() > MAX_BACKOFF_SECS {
+            backoff = MIN_BACKOFF_SECS;
+        }
+        log::info!("{:?} -- restarting in {} seconds", mint, backoff);
+        tokio::time::sleep(tokio::time::Duration::from_secs(backoff)).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF_SECS);
+    }
+}
+
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::mock::MockChain;
+    use crate::stakes::StakeIndex;
+
+    #[tokio::test(start_paused = true)]
+    async fn discover_partitions_and_filters_boosts() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (live, _) = chain.add_boost();
+        let (other, _) = chain.add_boost();
+        let (expiring, expiring_boost) = chain.add_boost();
+        chain.expire_boost(&expiring_boost);
+        let (active, expired) = discover(&client, None).await.unwrap();
+        assert_eq!(
+            active.into_iter().collect::<HashSet<_>>(),
+            HashSet::from([live, other])
+        );
+        assert_eq!(expired, vec![expiring_boost]);
+        // narrowed to the configured mints
+        let (active, expired) = discover(&client, Some(&[live, expiring])).await.unwrap();
+        assert_eq!(active, vec![live]);
+        assert_eq!(expired, vec![expiring_boost]);
+        let (active, expired) = discover(&client, Some(&[other])).await.unwrap();
+        assert_eq!(active, vec![other]);
+        assert!(expired.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconcile_starts_new_and_stops_gone_boosts() {
+        let chain = MockChain::new();
+        let client = Arc::new(chain.client());
+        let mut workers = HashMap::new();
+        let (first, first_boost) = chain.add_boost();
+        let (active, _) = discover(&client, None).await.unwrap();
+        reconcile(&client, &mut workers, active.as_slice());
+        assert_eq!(workers.keys().copied().collect::<Vec<_>>(), vec![first]);
+        let first_handle = workers[&first].abort_handle();
+        // a new boost is started, the running one left alone
+        let (second, second_boost) = chain.add_boost();
+        let (active, _) = discover(&client, None).await.unwrap();
+        reconcile(&client, &mut workers, active.as_slice());
+        assert_eq!(
+            workers.keys().copied().collect::<HashSet<_>>(),
+            HashSet::from([first, second])
+        );
+        let second_handle = workers[&second].abort_handle();
+        // expired and closed boosts are stopped
+        chain.expire_boost(&first_boost);
+        chain.remove_boost(&second_boost);
+        let (active, _) = discover(&client, None).await.unwrap();
+        reconcile(&client, &mut workers, active.as_slice());
+        assert!(workers.is_empty());
+        tokio::time::sleep(tokio::time::Duration::from_millis(1)).await;
+        assert!(first_handle.is_finished());
+        assert!(second_handle.is_finished());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gc_collects_expired_boosts_without_stake_accounts() {
+        let chain = MockChain::new();
+        let client = chain.client();
+        let (_, boost) = chain.add_boost();
+        let stakes = StakeIndex::new(&client, &boost).await.unwrap();
+        for _ in 0..300 {
+            chain.add_stake(&boost);
+        }
+        lookup_tables::sync(&client, &boost, &stakes).await.unwrap();
+        chain.expire_boost(&boost);
+        // every table retired despite its live stakers
+        gc(&client, &boost).await.unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
+        gc(&client, &boost).await.unwrap();
+        assert_eq!(chain.lookup_table_count(), 0);
+        // nothing left to collect
+        let (transactions, _) = chain.submissions();
+        gc(&client, &boost).await.unwrap();
+        assert_eq!(chain.submissions().0, transactions);
+    }
+}